@@ -14,9 +14,12 @@ use crate::{
     GameState,
     orrery::{BodyClass, Celestial, Orrery, Star},
     physics::WithinSoi,
-    precision::{FloatingOrigin, PreciseTransform, ToMetersExt, ToMillimetersExt},
+    precision::{
+        FloatOriginStrategy, FloatingOrigin, PRECISE_DISTANCE, PRECISE_VIEW_DISTANCE,
+        PreciseTransform, ToMetersExt, ToMillimetersExt,
+    },
 };
-use bevy::math::{DQuat, DVec3};
+use bevy::math::{DQuat, DVec3, I64Vec3};
 
 pub struct MainCameraPlugin;
 
@@ -29,7 +32,8 @@ pub struct CameraFocus;
 
 impl Plugin for MainCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Game), |mut commands: Commands| {
+        // as soon as loading is done, so that the scenario picker has something to draw on
+        app.add_systems(OnExit(GameState::Loading), |mut commands: Commands| {
             let k = (10_000.0f32).ln() / 144_000.0; // ≈ 6.14e-5
             commands.spawn((
                 MainCamera,
@@ -141,8 +145,9 @@ fn camera_controls(
 
     let (focus_ptf, soi_opt) = focus.into_inner();
     // Determine the "up" vector for the current local horizon.
-    let up: DVec3 = if let Some(WithinSoi(body_ent)) = soi_opt {
-        let cel_tf = celestials.get(*body_ent).unwrap();
+    let up: DVec3 = if let Some(WithinSoi(body_ent)) = soi_opt
+        && let Ok(cel_tf) = celestials.get(*body_ent)
+    {
         let delta_m = (focus_ptf.translation_mm - cel_tf.translation_mm).to_meters_64();
         delta_m.normalize()
     } else {
@@ -231,51 +236,159 @@ fn camera_lighting(
     }
 }
 
-/// Compute the floating origin and spawn. Currently, it's always the closest planet's closest surface.
+/// How far (m) the floating origin sits below the camera or the focused vessel, so that the camera is rendered above
+/// it and the atmosphere can tell its altitude.
+const ATMOSPHERE_DEPTH: f64 = 1000.0;
+const _: () = assert!(ATMOSPHERE_DEPTH < PRECISE_DISTANCE - PRECISE_VIEW_DISTANCE);
+
+/// Computes the floating origin with the selected strategy, and scales the atmosphere so that the camera's height
+/// over the origin maps to its altitude over the nearest planet.
 fn atmo_and_float_origin(
     star_sys: Res<Orrery>,
-    mut origin: ResMut<FloatingOrigin>,
-    camera: Single<&PreciseTransform, With<MainCamera>>,
+    (strategy, mut origin): (Res<FloatOriginStrategy>, ResMut<FloatingOrigin>),
+    mut focus_anchor: Local<Option<I64Vec3>>,
+    camera: Single<(&PreciseTransform, &mut AtmosphereSettings), With<MainCamera>>,
+    focus: Query<&PreciseTransform, With<CameraFocus>>,
     cel: Query<(&Celestial, &PreciseTransform)>,
 ) {
-    let camera_ptf = camera.into_inner();
-    // Find the planet whose surface is nearest the camera
-    let mut min_dist_surface = f64::MAX;
-    let mut best_origin = *camera_ptf;
+    let (camera_ptf, mut atmosphere) = camera.into_inner();
+    let camera_mm = camera_ptf.translation_mm;
+    // the planet whose surface is nearest the camera, as its centre and radius
+    let planet = cel
+        .iter()
+        .filter_map(|(cel_body, body_pt)| {
+            let body = star_sys.get_body(&cel_body.0)?;
+            matches!(body.class_params, BodyClass::Planet)
+                .then_some((body_pt.translation_mm, body.radius))
+        })
+        .min_by_key(|&(centre, radius)| {
+            OrderedFloat(((camera_mm - centre).to_meters_64().length() - radius).abs())
+        });
+    // “Up” direction (unit vector, away from the planet)
+    let up_at = |loc: I64Vec3| {
+        planet.map_or(DVec3::Y, |(centre, _)| {
+            (loc - centre).to_meters_64().normalize_or(DVec3::Y)
+        })
+    };
 
-    for (cel_body, body_pt) in cel.iter() {
-        let body = star_sys.get_body(&cel_body.0).unwrap();
-        if let BodyClass::Planet = body.class_params {
-            // Vector planet-centre → camera in metres.
-            let delta_m = (camera_ptf.translation_mm - body_pt.translation_mm).to_meters_64();
-            let dist_center = delta_m.length();
-            let radius = body.radius; // planet radius (m)
-            let dist_surface = (dist_center - radius).abs(); // camera altitude over surface
+    let (anchor, depth) = match *strategy {
+        FloatOriginStrategy::Camera => (camera_mm, ATMOSPHERE_DEPTH),
+        FloatOriginStrategy::Focus { recentre_distance } => {
+            let anchor = match (focus.single().ok(), *focus_anchor) {
+                (Some(focus), Some(anchor))
+                    if (focus.translation_mm - anchor).to_meters_64().length()
+                        <= recentre_distance =>
+                {
+                    anchor
+                }
+                (Some(focus), _) => focus.translation_mm,
+                (None, _) => camera_mm,
+            };
+            *focus_anchor = Some(anchor);
+            (anchor, ATMOSPHERE_DEPTH)
+        }
+        FloatOriginStrategy::Surface => match planet {
+            Some((centre, radius)) => (
+                centre.saturating_add((up_at(camera_mm) * radius).to_millimeters()),
+                0.0,
+            ),
+            None => (camera_mm, ATMOSPHERE_DEPTH),
+        },
+    };
+    let below = |anchor: I64Vec3, depth: f64| {
+        anchor.saturating_sub((up_at(anchor) * depth).to_millimeters())
+    };
+    let mut origin_mm = below(anchor, depth);
+    // whatever the strategy, what's rendered around the camera must keep sub-millimeter precision
+    if (camera_mm - origin_mm).to_meters_64().length() > PRECISE_DISTANCE - PRECISE_VIEW_DISTANCE {
+        origin_mm = below(camera_mm, ATMOSPHERE_DEPTH);
+    }
 
-            if dist_surface < min_dist_surface {
-                min_dist_surface = dist_surface;
+    // Align local Y to the up direction.
+    let up = up_at(origin_mm);
+    origin.0 = PreciseTransform {
+        translation_mm: origin_mm,
+        rotation: DQuat::from_rotation_arc(DVec3::Y, up),
+    };
 
-                // “Up” direction (unit vector, away from planet).
-                let up_dir = delta_m / dist_center;
+    if let Some((centre, radius)) = planet {
+        let altitude = (camera_mm - centre).to_meters_64().length() - radius;
+        let height = (camera_mm - origin_mm).to_meters_64().dot(up);
+        atmosphere.scene_units_to_m = (altitude.max(0.0) / height.max(1.0)) as f32;
+    }
+}
 
-                // Choose altitude for the floating origin: 100x smaller than the real origin
-                let origin_alt_m = dist_surface * 0.99;
+#[cfg(test)]
+mod tests {
+    use hifitime::Epoch;
 
-                // Position = planet centre + up_dir * (radius + origin_alt_m)
-                let origin_mm = body_pt
-                    .translation_mm
-                    .saturating_add((up_dir * (radius + origin_alt_m)).to_millimeters());
+    use super::*;
 
-                // Align local Y to the up direction.
-                let rotation = DQuat::from_rotation_arc(DVec3::Y, up_dir);
+    #[test]
+    fn origin_keeps_the_view_around_the_camera_precise() {
+        let yaml = r#"
+name: "empty"
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mass: "1 massSol"
+"#;
+        let orrery = Orrery::init(
+            serde_yml::from_str(yaml).unwrap(),
+            Epoch::from_tai_seconds(0.0),
+        )
+        .unwrap();
+        let mut app = App::new();
+        app.insert_resource(orrery)
+            .insert_resource(FloatOriginStrategy::default())
+            .insert_resource(FloatingOrigin(PreciseTransform::default()))
+            .add_systems(Update, atmo_and_float_origin);
+        let far_out = I64Vec3::new(1_496_000_000_000_000, -7_000_000_123, 42_000_000_017);
+        app.world_mut().spawn((
+            PreciseTransform {
+                translation_mm: far_out,
+                ..default()
+            },
+            CameraFocus,
+        ));
+        let camera = app
+            .world_mut()
+            .spawn((
+                MainCamera,
+                PreciseTransform::default(),
+                AtmosphereSettings::default(),
+            ))
+            .id();
 
-                best_origin = PreciseTransform {
-                    translation_mm: origin_mm,
-                    rotation,
-                };
+        // the camera pulls away from the vessel it follows, which the origin stays by as long as it can
+        for distance in [0.0, 2_000.0, 3_500.0, 5_000.0, 7_500.0, 20_000.0] {
+            let camera_mm = far_out + (DVec3::new(0.6, 0.0, 0.8) * distance).to_millimeters();
+            app.world_mut()
+                .get_mut::<PreciseTransform>(camera)
+                .unwrap()
+                .translation_mm = camera_mm;
+            app.update();
+
+            let origin = app.world().resource::<FloatingOrigin>();
+            let from_origin = (camera_mm - origin.0.translation_mm)
+                .to_meters_64()
+                .length();
+            assert!(
+                from_origin + PRECISE_VIEW_DISTANCE <= PRECISE_DISTANCE,
+                "camera {from_origin} m from the origin"
+            );
+            // so anything rendered around the camera projects to within half a millimeter
+            let inverse = origin.0.rotation.inverse();
+            for direction in [DVec3::X, DVec3::NEG_Y, DVec3::new(0.6, 0.0, 0.8)] {
+                let offset_mm = (direction * (PRECISE_VIEW_DISTANCE - 0.001)).to_millimeters()
+                    + I64Vec3::new(1, 1, 1);
+                let object_mm = camera_mm + offset_mm;
+                let projected = origin.project_loc(object_mm);
+                let exact = inverse * (object_mm - origin.0.translation_mm).to_meters_64();
+                let error = (projected.as_dvec3() - exact).abs().max_element();
+                assert!(error < 0.5e-3, "{error} m off at {distance} m");
             }
         }
     }
-
-    origin.0 = best_origin;
 }
@@ -10,11 +10,18 @@ use bevy_egui::{
 };
 
 use crate::{
+    GameState,
     camera::{CameraFocus, MainCamera},
     gui::hud::{bottom_hud, overlay_hud},
-    physics::aerodynamics::AeroEnv,
-    precision::{FloatingOrigin, PreciseTransform},
-    vessel::{ConsumableTanks, Thruster, VesselControls},
+    orrery::{StarSystems, SwitchStarSystem},
+    physics::{
+        aerodynamics::AeroEnv, geodesy::SurfaceState, orbit::PredictedOrbit, spatial::SpatialIndex,
+    },
+    precision::{
+        FloatOriginStrategy, FloatingOrigin, PRECISE_DISTANCE, PreciseTransform, ToMetersExt,
+    },
+    scenario::{Scenarios, StartScenario},
+    vessel::{ConsumableTanks, HotReload, PartHull, PartThermal, Thruster, VesselControls},
 };
 
 pub struct GuiPlugin;
@@ -24,26 +31,109 @@ impl Plugin for GuiPlugin {
         app.add_systems(
             EguiPrimaryContextPass,
             (
-                flight,
-                consumables,
-                diagnostics,
-                thrusters,
-                overlay_hud,
-                bottom_hud,
+                scenarios.run_if(in_state(GameState::Menu)),
+                (
+                    flight,
+                    consumables,
+                    diagnostics,
+                    thrusters,
+                    star_systems,
+                    overlay_hud,
+                    bottom_hud,
+                )
+                    .run_if(in_state(GameState::Game)),
             ),
         );
     }
 }
 
+type FocusedVessel = (
+    Entity,
+    &'static VesselControls,
+    &'static AeroEnv,
+    &'static PreciseTransform,
+    &'static Children,
+    &'static PredictedOrbit,
+    &'static SurfaceState,
+);
+
 fn flight(
     mut contexts: EguiContexts,
-    vessel: Single<(&VesselControls, &AeroEnv), With<CameraFocus>>,
+    vessel: Single<FocusedVessel, With<CameraFocus>>,
+    parts: Query<&PartThermal>,
+    hulls: Query<&PartHull>,
+    index: Res<SpatialIndex>,
 ) -> Result {
-    let (ctrl, aero) = vessel.into_inner();
+    let (entity, ctrl, aero, ptf, children, orbit, surface) = vessel.into_inner();
+    // the part closest to its temperature limit
+    let hottest = parts.iter_many(children).max_by(|a, b| {
+        (a.temperature / a.max_temperature).total_cmp(&(b.temperature / b.max_temperature))
+    });
+    // the outside pressure is the same for all parts, so the weakest hull is the closest to its rating
+    let weakest = hulls
+        .iter_many(children)
+        .map(|hull| hull.max_pressure)
+        .min_by(f64::total_cmp);
+    let nearest = index
+        .nearest_n(ptf.translation_mm, 2)
+        .into_iter()
+        .find(|nb| nb.entity != entity);
     let ctx = contexts.ctx_mut()?;
     egui::Window::new("Flight").show(ctx, |ui| {
         ui.label(format!("Altitude: {:.1} m", aero.altitude));
+        ui.label(format!(
+            "Position: {:.3}° {:.3}°",
+            surface.position.latitude.to_degrees(),
+            surface.position.longitude.to_degrees()
+        ));
+        ui.label(format!(
+            "Heading: {:.1}°, pitch: {:.1}°, roll: {:.1}°",
+            surface.attitude.heading.to_degrees(),
+            surface.attitude.pitch.to_degrees(),
+            surface.attitude.roll.to_degrees()
+        ));
+        ui.label(format!(
+            "Ground speed: {:.1} m/s, track {:.0}°",
+            surface.ground_speed(),
+            surface.track().to_degrees()
+        ));
+        ui.label(format!(
+            "Vertical speed: {:.1} m/s, flight path {:.1}°",
+            surface.vertical_speed(),
+            surface.flight_path_angle().to_degrees()
+        ));
+        if let Some(nearest) = nearest {
+            ui.label(format!(
+                "Nearest vessel: {:.1} m",
+                nearest.distance_mm / 1000.0
+            ));
+        }
+        ui.label(format!(
+            "Periapsis: {:.1} km",
+            orbit.periapsis_altitude() / 1000.0
+        ));
+        match orbit.apoapsis_altitude() {
+            Some(apoapsis) => ui.label(format!("Apoapsis: {:.1} km", apoapsis / 1000.0)),
+            None => ui.label(format!("Escape (e = {:.3})", orbit.conic.eccentricity)),
+        };
+        if let Some(time) = orbit.time_to_periapsis {
+            ui.label(format!("Time to periapsis: {time:.0} s"));
+        }
         ui.label(format!("True airspeed: {:.1} m/s", aero.airspeed.length()));
+        ui.label(format!("Wind: {:.1} m/s", aero.wind.length()));
+        if let Some(hottest) = hottest {
+            ui.label(format!(
+                "Skin temperature: {:.0} K (limit {:.0} K)",
+                hottest.temperature, hottest.max_temperature
+            ));
+        }
+        if let Some(weakest) = weakest {
+            ui.label(format!(
+                "Pressure: {:.1} kPa (hull limit {:.0} kPa)",
+                aero.pressure / 1000.0,
+                weakest / 1000.0
+            ));
+        }
         ui.label(format!(
             "Mach: {:.2}",
             aero.airspeed.length() / aero.speed_of_sound
@@ -72,6 +162,52 @@ fn flight(
     Ok(())
 }
 
+fn scenarios(
+    mut contexts: EguiContexts,
+    scenarios: Res<Scenarios>,
+    mut start: EventWriter<StartScenario>,
+) -> Result {
+    let ctx = contexts.ctx_mut()?;
+    egui::Window::new("Scenarios")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            if scenarios.available.is_empty() {
+                ui.label("No scenarios in assets/scenarios");
+            }
+            for name in &scenarios.available {
+                if ui.button(name.as_str()).clicked() {
+                    start.write(StartScenario(name.clone()));
+                }
+            }
+        });
+    Ok(())
+}
+
+fn star_systems(
+    mut contexts: EguiContexts,
+    systems: Res<StarSystems>,
+    mut switch: EventWriter<SwitchStarSystem>,
+) -> Result {
+    let ctx = contexts.ctx_mut()?;
+    egui::Window::new("Star system")
+        .default_open(false)
+        .show(ctx, |ui| {
+            let mut selected = systems.active.clone();
+            egui::ComboBox::from_id_salt("star_system")
+                .selected_text(selected.as_str())
+                .show_ui(ui, |ui| {
+                    for name in &systems.available {
+                        ui.selectable_value(&mut selected, name.clone(), name.as_str());
+                    }
+                });
+            if selected != systems.active {
+                switch.write(SwitchStarSystem(selected));
+            }
+        });
+    Ok(())
+}
+
 fn thrusters(
     mut contexts: EguiContexts,
     focused: Single<&Children, With<CameraFocus>>,
@@ -113,7 +249,8 @@ fn diagnostics(
     objects: Query<(), With<PreciseTransform>>,
     camera: Single<&PreciseTransform, With<MainCamera>>,
 
-    origin: Res<FloatingOrigin>,
+    (origin, mut strategy): (Res<FloatingOrigin>, ResMut<FloatOriginStrategy>),
+    mut hot_reload: ResMut<HotReload>,
 ) -> Result {
     let ctx = contexts.ctx_mut()?;
     egui::Window::new("Diagnostics").show(ctx, |ui| {
@@ -127,6 +264,36 @@ fn diagnostics(
             "Floating origin (mm): {:?}",
             origin.0.translation_mm
         ));
+        ui.label(format!(
+            "Camera from origin: {:.1} m",
+            (camera_xyz.translation_mm - origin.0.translation_mm)
+                .to_meters_64()
+                .length()
+        ));
+        let mut selected = *strategy;
+        ui.horizontal(|ui| {
+            ui.label("Floating origin");
+            ui.radio_value(&mut selected, FloatOriginStrategy::Camera, "Camera");
+            let focused = matches!(selected, FloatOriginStrategy::Focus { .. });
+            if ui.radio(focused, "Focused vessel").clicked() && !focused {
+                selected = FloatOriginStrategy::default();
+            }
+            ui.radio_value(
+                &mut selected,
+                FloatOriginStrategy::Surface,
+                "Planet surface",
+            );
+        });
+        if let FloatOriginStrategy::Focus { recentre_distance } = &mut selected {
+            ui.add(
+                egui::Slider::new(recentre_distance, 1.0..=PRECISE_DISTANCE)
+                    .logarithmic(true)
+                    .text("Recentre after (m)"),
+            );
+        }
+        if selected != *strategy {
+            *strategy = selected;
+        }
 
         if let Some(fps) = diagnostics
             .get(&FrameTimeDiagnosticsPlugin::FPS) // pick the diagnostic you want
@@ -134,6 +301,10 @@ fn diagnostics(
         {
             ui.label(format!("FPS: {fps:.1}"));
         }
+        ui.checkbox(
+            &mut hot_reload.respawn_vessels,
+            "Respawn vessels on config edits",
+        );
     });
 
     Ok(())
@@ -5,6 +5,7 @@ mod gui;
 mod orrery;
 mod physics;
 mod precision;
+mod scenario;
 mod vessel;
 
 use bevy::{
@@ -13,19 +14,39 @@ use bevy::{
 };
 use bevy_asset_loader::loading_state::{LoadingState, LoadingStateAppExt};
 use bevy_egui::{EguiGlobalSettings, EguiPlugin};
+use smol_str::SmolStr;
 
 use crate::{
     camera::MainCameraPlugin, gui::GuiPlugin, orrery::OrreryPlugin, physics::PhysicsPlugin,
-    precision::PrecisionPlugin, vessel::VesselsPlugin,
+    precision::PrecisionPlugin, scenario::ScenarioPlugin, vessel::VesselsPlugin,
 };
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 enum GameState {
     #[default]
     Loading,
+    /// Waiting for a scenario to be picked.
+    Menu,
     Game,
 }
 
+/// The value of the command-line option `--<name> <value>` or `--<name>=<value>`, or else of an environment variable.
+fn launch_option(name: &str, var: &str) -> Option<SmolStr> {
+    let flag = format!("--{name}");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix(&flag) {
+            if let Some(value) = value.strip_prefix('=') {
+                return Some(value.into());
+            }
+            if value.is_empty() {
+                return args.next().map(Into::into);
+            }
+        }
+    }
+    std::env::var(var).ok().map(Into::into)
+}
+
 /// Dummy non-send resource
 struct NonSendMarker;
 
@@ -43,7 +64,7 @@ fn main() {
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(Time::from_hz(101.0)) // a prime number
         .init_state::<GameState>()
-        .add_loading_state(LoadingState::new(GameState::Loading).continue_to_state(GameState::Game))
+        .add_loading_state(LoadingState::new(GameState::Loading).continue_to_state(GameState::Menu))
         .add_plugins((
             FrameTimeDiagnosticsPlugin::new(10000),
             AutoExposurePlugin,
@@ -59,6 +80,7 @@ fn main() {
             OrreryPlugin,
             PhysicsPlugin,
             VesselsPlugin,
+            ScenarioPlugin,
             GuiPlugin,
         ))
         // .add_plugins(WorldInspectorPlugin::new())
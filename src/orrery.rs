@@ -1,3 +1,6 @@
+mod harmonics;
+pub mod kepler;
+mod nbody;
 mod orrery_cfg;
 use bevy_asset_loader::{
     asset_collection::AssetCollection,
@@ -7,16 +10,30 @@ use bevy_asset_loader::{
     },
 };
 // Re-export planet classification for external use (e.g., camera behavior)
-pub use orrery_cfg::BodyClass;
+pub use orrery_cfg::{BodyClass, Orbit};
 pub use solver::Orrery;
 mod solver;
 
-use bevy::{pbr::NotShadowCaster, prelude::*};
+pub(crate) use orrery_cfg::{Angle, AngleUnit, de_angle, de_distance};
+
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    pbr::NotShadowCaster,
+    prelude::*,
+};
 use hifitime::Epoch;
 use smol_str::SmolStr;
 
 use crate::{
-    GameState, assets::TomlAssetLoader, orrery::orrery_cfg::OrreryCfg, physics::sim_time,
+    GameState, launch_option,
+    orrery::orrery_cfg::{Body, OrreryCfg},
+    physics::{
+        HasWithinSoi, SimClock, SimTime, WithinSoi,
+        aerodynamics::{AeroEnv, AtmosphereCfg, ProfileCfg},
+    },
     precision::PreciseTransform,
 };
 
@@ -28,84 +45,358 @@ impl Plugin for OrreryPlugin {
             LoadingStateConfig::new(GameState::Loading).load_collection::<StarSysAssets>(),
         )
         .init_asset::<OrreryCfg>()
-        .register_asset_loader(TomlAssetLoader::<OrreryCfg>::new("star.toml"))
+        .register_asset_loader(OrreryCfgLoader)
+        .add_event::<SwitchStarSystem>()
+        .insert_resource(StarSystems {
+            available: vec![],
+            active: requested_system().unwrap_or_else(|| DEFAULT_SYSTEM.into()),
+        })
+        .init_resource::<CelestialRender>()
         .add_systems(OnEnter(GameState::Game), load_orrery)
+        .add_systems(
+            Update,
+            (reload_star_systems, switch_star_system)
+                .chain()
+                .run_if(in_state(GameState::Game)),
+        )
+        // before anything else in the tick, so that every query hits the cache
+        .add_systems(
+            FixedPreUpdate,
+            advance_orrery.run_if(in_state(GameState::Game)),
+        )
         .add_systems(FixedUpdate, move_orrery.run_if(in_state(GameState::Game)));
     }
 }
 
+/// Asset loader for star system configuration files, which also reads the data files they reference.
+struct OrreryCfgLoader;
+
+impl AssetLoader for OrreryCfgLoader {
+    type Asset = OrreryCfg;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut cfg = toml::from_slice::<OrreryCfg>(&bytes)?;
+        for body in cfg.bodies.iter_mut() {
+            if let Some(AtmosphereCfg {
+                profile: ProfileCfg::Table(table),
+                ..
+            }) = &mut body.atmosphere
+                && let Some(file) = &table.file
+            {
+                // paths are relative to the configuration file
+                let path = load_context.asset_path().resolve_embed(file)?;
+                let text = String::from_utf8(load_context.read_asset_bytes(&path).await?)?;
+                table
+                    .read_csv(&text)
+                    .with_context(|| format!("bad atmosphere table {path}"))?;
+            }
+        }
+        Ok(cfg)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["star.toml"]
+    }
+}
+
+fn advance_orrery(mut star_sys: ResMut<Orrery>, time: SimTime) {
+    star_sys.advance(time.epoch());
+}
+
 fn move_orrery(
     star_sys: Res<Orrery>,
-    time: Res<Time>,
+    time: SimTime,
     mut bodies: Query<(&Celestial, &mut PreciseTransform)>,
 ) {
-    let epoch = sim_time(&time);
+    let epoch = time.epoch();
     for (body, mut ptf) in bodies.iter_mut() {
         ptf.translation_mm = star_sys.solve_position(&body.0, epoch).unwrap();
         ptf.rotation = star_sys.solve_rotation(&body.0, epoch).unwrap();
     }
 }
 
-/// Temporary resource holding the handle to the star system configuration asset.
+/// The star system simulated when none is asked for.
+const DEFAULT_SYSTEM: &str = "Taale";
+/// Environment variable naming the star system to simulate, overridden by the `--system` argument.
+const SYSTEM_VAR: &str = "TOY_SIM_SYSTEM";
+
+/// Handles to every star system configuration in the `stars` folder.
 #[derive(Resource, AssetCollection)]
 struct StarSysAssets {
-    #[asset(path = "stars/taale.star.toml")]
-    taale: Handle<OrreryCfg>,
+    #[asset(path = "stars", collection(typed))]
+    systems: Vec<Handle<OrreryCfg>>,
+}
+
+/// The star systems that can be simulated, and the one that is.
+#[derive(Resource, Debug)]
+pub struct StarSystems {
+    /// Names of every loaded star system, sorted.
+    pub available: Vec<SmolStr>,
+    pub active: SmolStr,
+}
+
+/// Replaces the simulated star system by another, given by name.
+#[derive(Event, Clone, Debug)]
+pub struct SwitchStarSystem(pub SmolStr);
+
+/// The star system asked for with `--system <name>` on the command line, or else in the environment.
+fn requested_system() -> Option<SmolStr> {
+    launch_option("system", SYSTEM_VAR)
+}
+
+/// Finds a loaded star system configuration by name, ignoring case.
+fn find_system<'a>(
+    assets: &StarSysAssets,
+    cfgs: &'a Assets<OrreryCfg>,
+    name: &str,
+) -> Option<&'a OrreryCfg> {
+    assets
+        .systems
+        .iter()
+        .filter_map(|handle| cfgs.get(handle))
+        .find(|cfg| cfg.name.eq_ignore_ascii_case(name))
 }
 
-/// Once the star system configuration asset is loaded, initializes the star system and spawns celestial bodies.
+/// Once the star system configurations are loaded, initializes the chosen star system and spawns its bodies.
 fn load_orrery(
     mut commands: Commands,
-    cfg_handle: Res<StarSysAssets>,
+    assets: Res<StarSysAssets>,
     cfgs: Res<Assets<OrreryCfg>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut systems: ResMut<StarSystems>,
+    render: Res<CelestialRender>,
+    clock: Res<SimClock>,
 ) {
-    let cfg = cfgs.get(&cfg_handle.taale).unwrap();
+    systems.available = available_systems(&assets, &cfgs);
+    let cfg = find_system(&assets, &cfgs, &systems.active)
+        .or_else(|| {
+            warn!(
+                "no star system named {}; falling back to {DEFAULT_SYSTEM}",
+                systems.active
+            );
+            find_system(&assets, &cfgs, DEFAULT_SYSTEM)
+        })
+        .expect("the default star system is missing");
+    systems.active = cfg.name.clone();
     info!("starting star loading");
-    let star_sys = Orrery::init(cfg.clone()).unwrap();
-
-    let unit_sphere = Mesh3d(meshes.add(Sphere { radius: 1.0 }.mesh().uv(256, 256)));
-    let gray = MeshMaterial3d(materials.add(Color::srgb_u8(128, 128, 128)));
-    let star = MeshMaterial3d(materials.add(StandardMaterial {
-        emissive: LinearRgba::WHITE * 100.0,
-        emissive_exposure_weight: 0.0,
-        ..default()
-    }));
-
-    for (idx, body) in star_sys.iter().enumerate() {
-        let posn = star_sys
-            .solve_position(&body.name, Epoch::from_utc_days(0.0))
-            .unwrap();
+    let star_sys = Orrery::init(cfg.clone(), clock.start).unwrap();
+    spawn_star_system(&mut commands, star_sys, &render, clock.start);
+}
 
-        let mut entity = commands.spawn((
-            Celestial(body.name.clone()),
-            unit_sphere.clone(),
-            gray.clone(),
-            PreciseTransform {
-                translation_mm: posn,
-                ..default()
-            },
-            Transform {
-                scale: Vec3::from_array([body.radius as _, body.radius as _, body.radius as _]),
-                ..default()
-            },
-        ));
+/// Replaces the star system: despawns the bodies of the current one, and rebuilds the solver and the bodies from the
+/// new configuration. Vessels stay where they are, outside of any sphere of influence until gravity finds them one.
+fn switch_star_system(
+    mut commands: Commands,
+    mut events: EventReader<SwitchStarSystem>,
+    assets: Res<StarSysAssets>,
+    cfgs: Res<Assets<OrreryCfg>>,
+    mut systems: ResMut<StarSystems>,
+    celestials: Query<(Entity, Option<&HasWithinSoi>), With<Celestial>>,
+    (render, clock, time): (Res<CelestialRender>, Res<SimClock>, SimTime),
+) {
+    // only the latest request matters
+    let Some(SwitchStarSystem(name)) = events.read().last() else {
+        return;
+    };
+    if name.eq_ignore_ascii_case(&systems.active) {
+        return;
+    }
+    let Some(cfg) = find_system(&assets, &cfgs, name) else {
+        error!("cannot switch to unknown star system {name}");
+        return;
+    };
+    let star_sys = match Orrery::init(cfg.clone(), clock.start) {
+        Ok(star_sys) => star_sys,
+        Err(err) => {
+            error!("cannot switch to star system {name}: {err:#}");
+            return;
+        }
+    };
+    info!("switching from {} to {}", systems.active, cfg.name);
+    for (entity, within) in &celestials {
+        despawn_body(&mut commands, entity, within);
+    }
+    systems.active = cfg.name.clone();
+    spawn_star_system(&mut commands, star_sys, &render, time.epoch());
+}
 
-        if let BodyClass::Star { lumens } = body.class_params {
-            entity.insert((
-                star.clone(),
-                Star {
-                    lumens,
-                    color_temp: 5000.0,
-                },
-                NotShadowCaster,
-            ));
+/// Picks up edits to star system configurations. The active system's solver is rebuilt, and its bodies are updated
+/// in place, so that whatever refers to them carries on. Bodies that were removed, or became or stopped being stars,
+/// are despawned like on a switch.
+fn reload_star_systems(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<OrreryCfg>>,
+    assets: Res<StarSysAssets>,
+    cfgs: Res<Assets<OrreryCfg>>,
+    mut systems: ResMut<StarSystems>,
+    mut celestials: Query<(Entity, &Celestial, &mut Transform, Option<&mut Star>)>,
+    (within_soi, render, clock, time): (
+        Query<&HasWithinSoi>,
+        Res<CelestialRender>,
+        Res<SimClock>,
+        SimTime,
+    ),
+) {
+    let mut active = None;
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event
+            && let Some(cfg) = cfgs.get(*id)
+            && cfg.name.eq_ignore_ascii_case(&systems.active)
+        {
+            active = Some(cfg);
         }
     }
+    systems.available = available_systems(&assets, &cfgs);
+    let Some(cfg) = active else {
+        return;
+    };
+    let star_sys = match Orrery::init(cfg.clone(), clock.start) {
+        Ok(star_sys) => star_sys,
+        Err(err) => {
+            error!("cannot reload star system {}: {err:#}", cfg.name);
+            return;
+        }
+    };
+    info!("reloading star system {}", cfg.name);
+
+    let mut kept = BTreeSet::new();
+    for (entity, celestial, mut transform, star) in &mut celestials {
+        let within = within_soi.get(entity).ok();
+        let Some(body) = star_sys.get_body(&celestial.0) else {
+            despawn_body(&mut commands, entity, within);
+            continue;
+        };
+        match (&body.class_params, star) {
+            (BodyClass::Star { lumens }, Some(mut star)) => star.lumens = *lumens,
+            (BodyClass::Planet, None) => {}
+            // a body that became or stopped being a star is spawned anew
+            _ => {
+                despawn_body(&mut commands, entity, within);
+                continue;
+            }
+        }
+        transform.scale = Vec3::splat(body.radius as f32);
+        kept.insert(body.name.clone());
+    }
+    for body in star_sys.iter().filter(|body| !kept.contains(&body.name)) {
+        spawn_body(&mut commands, &star_sys, body, &render, time.epoch());
+    }
     commands.insert_resource(star_sys);
 }
 
+/// Despawns a body, taking the vessels within its sphere of influence out of it first, so that nothing looks the body
+/// up before gravity has found them another.
+fn despawn_body(commands: &mut Commands, body: Entity, within: Option<&HasWithinSoi>) {
+    for vessel in within.into_iter().flat_map(|within| within.iter()) {
+        commands
+            .entity(vessel)
+            .remove::<WithinSoi>()
+            .entry::<AeroEnv>()
+            .and_modify(|mut aero| aero.planet = SmolStr::default());
+    }
+    commands.entity(body).despawn();
+}
+
+/// Names of every loaded star system, sorted.
+fn available_systems(assets: &StarSysAssets, cfgs: &Assets<OrreryCfg>) -> Vec<SmolStr> {
+    let mut names = assets
+        .systems
+        .iter()
+        .filter_map(|handle| cfgs.get(handle))
+        .map(|cfg| cfg.name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// The mesh and materials shared by all celestial bodies.
+#[derive(Resource)]
+struct CelestialRender {
+    unit_sphere: Mesh3d,
+    gray: MeshMaterial3d<StandardMaterial>,
+    star: MeshMaterial3d<StandardMaterial>,
+}
+
+impl FromWorld for CelestialRender {
+    fn from_world(world: &mut World) -> Self {
+        let unit_sphere = Mesh3d(
+            world
+                .resource_mut::<Assets<Mesh>>()
+                .add(Sphere { radius: 1.0 }.mesh().uv(256, 256)),
+        );
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let gray = MeshMaterial3d(materials.add(Color::srgb_u8(128, 128, 128)));
+        let star = MeshMaterial3d(materials.add(StandardMaterial {
+            emissive: LinearRgba::WHITE * 100.0,
+            emissive_exposure_weight: 0.0,
+            ..default()
+        }));
+        Self {
+            unit_sphere,
+            gray,
+            star,
+        }
+    }
+}
+
+/// Spawns the bodies of a star system where they are at an epoch, and makes its solver the active one.
+fn spawn_star_system(
+    commands: &mut Commands,
+    star_sys: Orrery,
+    render: &CelestialRender,
+    epoch: Epoch,
+) {
+    for body in star_sys.iter() {
+        spawn_body(commands, &star_sys, body, render, epoch);
+    }
+    commands.insert_resource(star_sys);
+}
+
+/// Spawns a body where it is at an epoch, so that nothing sees it out of place before `move_orrery` runs.
+fn spawn_body(
+    commands: &mut Commands,
+    star_sys: &Orrery,
+    body: &Body,
+    render: &CelestialRender,
+    epoch: Epoch,
+) {
+    let posn = star_sys.solve_position(&body.name, epoch).unwrap();
+    let rotation = star_sys.solve_rotation(&body.name, epoch).unwrap();
+
+    let mut entity = commands.spawn((
+        Celestial(body.name.clone()),
+        render.unit_sphere.clone(),
+        render.gray.clone(),
+        PreciseTransform {
+            translation_mm: posn,
+            rotation,
+        },
+        Transform {
+            scale: Vec3::from_array([body.radius as _, body.radius as _, body.radius as _]),
+            ..default()
+        },
+    ));
+
+    if let BodyClass::Star { lumens } = body.class_params {
+        entity.insert((
+            render.star.clone(),
+            Star {
+                lumens,
+                color_temp: 5000.0,
+            },
+            NotShadowCaster,
+        ));
+    }
+}
+
 #[derive(Component, Default)]
 pub struct Celestial(pub SmolStr);
 
@@ -115,3 +406,164 @@ pub struct Star {
     pub lumens: f64,
     pub color_temp: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(name: &str, planet: &str) -> OrreryCfg {
+        serde_yml::from_str(&format!(
+            r#"
+name: "{name}"
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mass: "1 massSol"
+  - name: "{planet}"
+    class: planet
+    parent: "Star"
+    mass: "1 massEarth"
+    radius: 6.4e6
+    semi_major: "1 au"
+"#
+        ))
+        .unwrap()
+    }
+
+    /// An app simulating the first of two star systems, with a vessel within the sphere of influence of its planet.
+    fn setup() -> (App, [AssetId<OrreryCfg>; 2], Entity) {
+        let mut app = App::new();
+        let mut cfgs = Assets::<OrreryCfg>::default();
+        let systems = [
+            cfgs.add(system("One", "Planet")),
+            cfgs.add(system("Two", "Other")),
+        ];
+        let ids = systems.each_ref().map(Handle::id);
+        app.add_event::<SwitchStarSystem>()
+            .add_event::<AssetEvent<OrreryCfg>>()
+            .insert_resource(cfgs)
+            .insert_resource(StarSysAssets {
+                systems: systems.into(),
+            })
+            .insert_resource(StarSystems {
+                available: vec![],
+                active: "One".into(),
+            })
+            .insert_resource(CelestialRender {
+                unit_sphere: Mesh3d(Handle::default()),
+                gray: MeshMaterial3d(Handle::default()),
+                star: MeshMaterial3d(Handle::default()),
+            })
+            .insert_resource(SimClock::default())
+            .insert_resource(Time::<()>::default())
+            .add_systems(Update, (reload_star_systems, switch_star_system).chain());
+
+        let world = app.world_mut();
+        let star_sys = Orrery::init(system("One", "Planet"), Epoch::from_tai_seconds(0.0)).unwrap();
+        let render = world.remove_resource::<CelestialRender>().unwrap();
+        spawn_star_system(
+            &mut world.commands(),
+            star_sys,
+            &render,
+            Epoch::from_tai_seconds(0.0),
+        );
+        world.insert_resource(render);
+        world.flush();
+        let planet = world
+            .query::<(Entity, &Celestial)>()
+            .iter(world)
+            .find(|(_, celestial)| celestial.0 == "Planet")
+            .unwrap()
+            .0;
+        let vessel = world
+            .spawn((
+                WithinSoi(planet),
+                AeroEnv {
+                    planet: "Planet".into(),
+                    ..Default::default()
+                },
+            ))
+            .id();
+        (app, ids, vessel)
+    }
+
+    fn bodies(app: &mut App) -> Vec<SmolStr> {
+        let world = app.world_mut();
+        let mut names = world
+            .query::<&Celestial>()
+            .iter(world)
+            .map(|celestial| celestial.0.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn switching_systems_leaves_vessels_outside_any_soi() {
+        let (mut app, _, vessel) = setup();
+        app.world_mut().send_event(SwitchStarSystem("two".into()));
+        app.update();
+
+        assert_eq!(app.world().resource::<StarSystems>().active, "Two");
+        assert_eq!(bodies(&mut app), ["Other", "Star"]);
+        assert!(app.world().get::<WithinSoi>(vessel).is_none());
+        assert_eq!(app.world().get::<AeroEnv>(vessel).unwrap().planet, "");
+    }
+
+    #[test]
+    fn reloading_a_system_without_a_body_leaves_its_soi() {
+        let (mut app, [one, _], vessel) = setup();
+        let world = app.world_mut();
+        world
+            .resource_mut::<Assets<OrreryCfg>>()
+            .insert(one, system("One", "Renamed"));
+        world.send_event(AssetEvent::Modified { id: one });
+        app.update();
+
+        assert_eq!(bodies(&mut app), ["Renamed", "Star"]);
+        assert!(app.world().get::<WithinSoi>(vessel).is_none());
+        assert_eq!(app.world().get::<AeroEnv>(vessel).unwrap().planet, "");
+        assert!(
+            app.world()
+                .resource::<Orrery>()
+                .get_body("Renamed")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn bodies_start_where_they_are_at_the_clock_start() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let (mut app, ..) = setup();
+        let start = Epoch::from_gregorian_utc_at_midnight(2100, 1, 1);
+        let world = app.world_mut();
+        world.resource_mut::<SimClock>().start = start;
+        let old = world
+            .query_filtered::<Entity, With<Celestial>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for entity in old {
+            world.despawn(entity);
+        }
+        world.run_system_once(load_orrery).unwrap();
+
+        let mut planets = world.query::<(&Celestial, &PreciseTransform)>();
+        let (_, ptf) = planets
+            .iter(world)
+            .find(|(celestial, _)| celestial.0 == "Planet")
+            .unwrap();
+        let orrery = world.resource::<Orrery>();
+        assert_eq!(
+            ptf.translation_mm,
+            orrery.solve_position("Planet", start).unwrap()
+        );
+        assert_ne!(
+            ptf.translation_mm,
+            orrery
+                .solve_position("Planet", Epoch::from_utc_days(0.0))
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::math::DVec3;
+use serde::{Deserialize, Serialize};
+
+/// Zonal harmonics of a body's gravity field, describing its departure from a point mass due to oblateness and
+/// north–south asymmetry. They are relative to the body's radius and spin axis.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct ZonalHarmonics {
+    #[serde(default)]
+    pub j2: f64,
+    #[serde(default)]
+    pub j3: f64,
+    #[serde(default)]
+    pub j4: f64,
+}
+
+impl ZonalHarmonics {
+    pub fn is_zero(&self) -> bool {
+        self.j2 == 0.0 && self.j3 == 0.0 && self.j4 == 0.0
+    }
+
+    /// Acceleration (m/s²) on top of the point-mass gravity, at a position (m) in the body-fixed frame whose Z axis is
+    /// the spin axis, for a body of gravitational parameter `mu` (m³/s²) and reference radius `radius` (m).
+    pub fn acceleration(&self, mu: f64, radius: f64, position: DVec3) -> DVec3 {
+        let r2 = position.length_squared();
+        if self.is_zero() || r2 == 0.0 {
+            return DVec3::ZERO;
+        }
+        let r = r2.sqrt();
+        let DVec3 { x, y, z } = position;
+        let s = z / r;
+        let s2 = s * s;
+        let k = mu / r2;
+        let ratio = radius / r;
+
+        // each term is the gradient of -μ/r · Jn (R/r)ⁿ Pn(z/r)
+        let mut acc = DVec3::ZERO;
+
+        let c2 = -1.5 * self.j2 * k * ratio.powi(2) / r;
+        acc += c2
+            * DVec3::new(
+                x * (1.0 - 5.0 * s2),
+                y * (1.0 - 5.0 * s2),
+                z * (3.0 - 5.0 * s2),
+            );
+
+        let c3 = -2.5 * self.j3 * k * ratio.powi(3) / r;
+        acc += c3
+            * DVec3::new(
+                x * s * (3.0 - 7.0 * s2),
+                y * s * (3.0 - 7.0 * s2),
+                r * (6.0 * s2 - 7.0 * s2 * s2 - 0.6),
+            );
+
+        let c4 = 1.875 * self.j4 * k * ratio.powi(4) / r;
+        let planar = 1.0 - 14.0 * s2 + 21.0 * s2 * s2;
+        acc += c4
+            * DVec3::new(
+                x * planar,
+                y * planar,
+                z * (5.0 - 70.0 / 3.0 * s2 + 21.0 * s2 * s2),
+            );
+
+        acc
+    }
+
+    /// Secular drift (rad/s) of the ascending node and of the argument of pericenter of an orbit, due to J2.
+    ///
+    /// `mean_motion` is in rad/s, `semi_latus` (the semi-latus rectum a(1 − e²)) and `radius` are in m, and
+    /// `cos_inclination` is measured against the body's equator.
+    pub fn secular_rates(
+        &self,
+        radius: f64,
+        mean_motion: f64,
+        semi_latus: f64,
+        cos_inclination: f64,
+    ) -> (f64, f64) {
+        if semi_latus <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let k = mean_motion * self.j2 * (radius / semi_latus).powi(2);
+        let node = -1.5 * k * cos_inclination;
+        let pericenter = 0.75 * k * (5.0 * cos_inclination * cos_inclination - 1.0);
+        (node, pericenter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Potential (m²/s²) of the harmonics alone, whose gradient is their acceleration.
+    fn potential(h: &ZonalHarmonics, mu: f64, radius: f64, position: DVec3) -> f64 {
+        let r = position.length();
+        let s = position.z / r;
+        let p2 = 0.5 * (3.0 * s * s - 1.0);
+        let p3 = 0.5 * (5.0 * s.powi(3) - 3.0 * s);
+        let p4 = (35.0 * s.powi(4) - 30.0 * s * s + 3.0) / 8.0;
+        let ratio = radius / r;
+        -mu / r
+            * (h.j2 * ratio.powi(2) * p2 + h.j3 * ratio.powi(3) * p3 + h.j4 * ratio.powi(4) * p4)
+    }
+
+    #[test]
+    fn acceleration_is_gradient_of_potential() {
+        let h = ZonalHarmonics {
+            j2: 1.0826e-3,
+            j3: -2.53e-6,
+            j4: -1.62e-6,
+        };
+        let (mu, radius) = (3.986e14, 6.378e6);
+        for position in [
+            DVec3::new(7.0e6, 0.0, 0.0),
+            DVec3::new(4.0e6, -3.0e6, 5.0e6),
+            DVec3::new(-1.0e6, 2.0e6, -6.5e6),
+        ] {
+            let step = 1.0;
+            let gradient = DVec3::new(
+                potential(&h, mu, radius, position + DVec3::X * step)
+                    - potential(&h, mu, radius, position - DVec3::X * step),
+                potential(&h, mu, radius, position + DVec3::Y * step)
+                    - potential(&h, mu, radius, position - DVec3::Y * step),
+                potential(&h, mu, radius, position + DVec3::Z * step)
+                    - potential(&h, mu, radius, position - DVec3::Z * step),
+            ) / (2.0 * step);
+            let acc = h.acceleration(mu, radius, position);
+            assert!(
+                (acc - gradient).length() < 1e-6 * acc.length(),
+                "{acc} vs {gradient}"
+            );
+        }
+    }
+}
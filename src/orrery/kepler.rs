@@ -0,0 +1,289 @@
+use std::f64::consts::{PI, TAU};
+
+use bevy::math::DVec3;
+
+/// Largest correction (rad) to the eccentric anomaly at which Newton's method is deemed converged.
+const TOLERANCE: f64 = 1e-14;
+const MAX_ITERATIONS: usize = 50;
+/// Distance of the eccentricity from 1 below which an orbit is treated as parabolic.
+const PARABOLIC: f64 = 1e-9;
+
+/// Solves Kepler's equation M = E − e sin E for the eccentric anomaly (rad) of an elliptic orbit, by Newton's
+/// method. The result is wrapped to [−π, π].
+#[allow(non_snake_case)]
+pub fn eccentric_anomaly(mean_anomaly: f64, e: f64) -> f64 {
+    let m = (mean_anomaly + PI).rem_euclid(TAU) - PI;
+    // starting from π converges for any eccentricity, but more slowly at low ones
+    let mut E = if e < 0.8 { m } else { PI.copysign(m) };
+    for _ in 0..MAX_ITERATIONS {
+        let step = (E - e * E.sin() - m) / (1.0 - e * E.cos());
+        E -= step;
+        if step.abs() < TOLERANCE {
+            break;
+        }
+    }
+    E
+}
+
+/// Position (m) and velocity (m/s) on an elliptic orbit around a body of gravitational parameter `mu` (m³/s²), in
+/// the perifocal frame: pericenter along X, and motion towards +Y.
+#[allow(non_snake_case)]
+pub fn perifocal_state(semi_major: f64, e: f64, mu: f64, mean_anomaly: f64) -> (DVec3, DVec3) {
+    let E = eccentric_anomaly(mean_anomaly, e);
+    let (sin_E, cos_E) = E.sin_cos();
+    let root = (1.0 - e * e).sqrt();
+    let r = semi_major * (1.0 - e * cos_E);
+    let position = DVec3::new(semi_major * (cos_E - e), semi_major * root * sin_E, 0.0);
+    let velocity = DVec3::new(-sin_E, root * cos_E, 0.0) * ((mu * semi_major).sqrt() / r);
+    (position, velocity)
+}
+
+/// Stumpff functions C(ψ) and S(ψ) of the universal-variable formulation. Near ψ = 0, where the closed forms lose
+/// their precision to cancellation, they are summed from their series.
+pub fn stumpff(psi: f64) -> (f64, f64) {
+    if psi > 1.0 {
+        let root = psi.sqrt();
+        ((1.0 - root.cos()) / psi, (root - root.sin()) / (psi * root))
+    } else if psi < -1.0 {
+        let root = (-psi).sqrt();
+        (
+            (1.0 - root.cosh()) / psi,
+            (root.sinh() - root) / (-psi * root),
+        )
+    } else {
+        // C = Σ (−ψ)ᵏ / (2k + 2)!, S = Σ (−ψ)ᵏ / (2k + 3)!
+        let (mut c, mut s) = (0.0, 0.0);
+        let (mut c_term, mut s_term) = (0.5, 1.0 / 6.0);
+        for k in 0..12 {
+            c += c_term;
+            s += s_term;
+            let k = k as f64;
+            c_term *= -psi / ((2.0 * k + 3.0) * (2.0 * k + 4.0));
+            s_term *= -psi / ((2.0 * k + 4.0) * (2.0 * k + 5.0));
+        }
+        (c, s)
+    }
+}
+
+/// Propagates a position (m) and velocity (m/s) relative to a body of gravitational parameter `mu` (m³/s²) by `dt`
+/// seconds along their conic, whatever its eccentricity, with the universal-variable formulation.
+pub fn propagate(mu: f64, position: DVec3, velocity: DVec3, dt: f64) -> (DVec3, DVec3) {
+    let r0 = position.length();
+    if dt == 0.0 || mu <= 0.0 || r0 == 0.0 {
+        return (position + velocity * dt, velocity);
+    }
+    let sqrt_mu = mu.sqrt();
+    let radial = position.dot(velocity) / sqrt_mu;
+    // reciprocal of the semi-major axis: positive for ellipses, negative for hyperbolas
+    let alpha = 2.0 / r0 - velocity.length_squared() / mu;
+
+    let mut chi = if alpha > 1e-12 {
+        sqrt_mu * dt * alpha
+    } else if alpha < -1e-12 {
+        let a = 1.0 / alpha;
+        let guess = dt.signum()
+            * (-a).sqrt()
+            * ((-2.0 * mu * alpha * dt)
+                / (position.dot(velocity) + dt.signum() * (-mu * a).sqrt() * (1.0 - r0 * alpha)))
+                .ln();
+        if guess.is_finite() {
+            guess
+        } else {
+            sqrt_mu * dt / r0
+        }
+    } else {
+        // Barker's equation solved exactly for the parabola through the same semi-latus rectum
+        let p = position.cross(velocity).length_squared() / mu;
+        let s = 0.5 * (1.0 / (3.0 * (mu / p.powi(3)).sqrt() * dt)).atan();
+        let w = s.tan().cbrt().atan();
+        p.sqrt() * 2.0 / (2.0 * w).tan()
+    };
+
+    let mut r = r0;
+    let (mut c, mut s) = (0.5, 1.0 / 6.0);
+    for _ in 0..MAX_ITERATIONS {
+        let psi = chi * chi * alpha;
+        (c, s) = stumpff(psi);
+        let chi2 = chi * chi;
+        r = chi2 * c + radial * chi * (1.0 - psi * s) + r0 * (1.0 - psi * c);
+        let residual =
+            sqrt_mu * dt - chi2 * chi * s - radial * chi2 * c - r0 * chi * (1.0 - psi * s);
+        let step = residual / r;
+        chi += step;
+        if step.abs() <= 1e-12 * chi.abs().max(1.0) {
+            break;
+        }
+    }
+
+    let chi2 = chi * chi;
+    let psi = chi2 * alpha;
+    let f = 1.0 - chi2 / r0 * c;
+    let g = dt - chi2 * chi / sqrt_mu * s;
+    let f_dot = sqrt_mu / (r * r0) * chi * (psi * s - 1.0);
+    let g_dot = 1.0 - chi2 / r * c;
+    (
+        position * f + velocity * g,
+        position * f_dot + velocity * g_dot,
+    )
+}
+
+/// Shape of a conic around a body.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Conic {
+    /// Distance (m) of the periapsis from the body's centre.
+    pub periapsis: f64,
+    pub eccentricity: f64,
+}
+
+impl Conic {
+    /// The conic through a position (m) and velocity (m/s) relative to a body of gravitational parameter `mu`.
+    pub fn from_state(mu: f64, position: DVec3, velocity: DVec3) -> Self {
+        let h = position.cross(velocity);
+        let e = (velocity.cross(h) / mu - position.normalize_or_zero()).length();
+        Self {
+            periapsis: h.length_squared() / (mu * (1.0 + e)),
+            eccentricity: e,
+        }
+    }
+
+    /// Semi-major axis (m): negative for hyperbolas, and infinite for parabolas.
+    pub fn semi_major(&self) -> f64 {
+        if self.is_parabolic() {
+            return f64::INFINITY;
+        }
+        self.periapsis / (1.0 - self.eccentricity)
+    }
+
+    /// Distance (m) of the apoapsis, for closed orbits.
+    pub fn apoapsis(&self) -> Option<f64> {
+        (self.eccentricity < 1.0 - PARABOLIC).then(|| self.semi_major() * (1.0 + self.eccentricity))
+    }
+
+    pub fn is_parabolic(&self) -> bool {
+        (self.eccentricity - 1.0).abs() < PARABOLIC
+    }
+
+    /// Rate (rad/s) of the mean anomaly. For parabolas, it is √(μ / 2q³), which makes the mean anomaly the
+    /// D + D³/3 of Barker's equation, with D = tan(ν/2).
+    pub fn mean_motion(&self, mu: f64) -> f64 {
+        if self.is_parabolic() {
+            return (mu / (2.0 * self.periapsis.powi(3))).sqrt();
+        }
+        (mu / self.semi_major().abs().powi(3)).sqrt()
+    }
+
+    /// Position (m) and velocity (m/s) at periapsis, in the perifocal frame.
+    pub fn periapsis_state(&self, mu: f64) -> (DVec3, DVec3) {
+        let speed = (mu * (1.0 + self.eccentricity) / self.periapsis).sqrt();
+        (DVec3::X * self.periapsis, DVec3::Y * speed)
+    }
+
+    /// Position (m) and velocity (m/s) in the perifocal frame at a mean anomaly (rad), measured from periapsis.
+    /// Unlike [`perifocal_state`], this holds for open orbits too.
+    pub fn perifocal_state(&self, mu: f64, mean_anomaly: f64) -> (DVec3, DVec3) {
+        let (position, velocity) = self.periapsis_state(mu);
+        propagate(mu, position, velocity, mean_anomaly / self.mean_motion(mu))
+    }
+}
+
+/// Time (s) since the last periapsis passage of a position (m) and velocity (m/s) relative to a body, or until the
+/// next one if negative. On closed orbits, it lies within half a period of zero.
+#[allow(non_snake_case)]
+pub fn time_since_periapsis(mu: f64, position: DVec3, velocity: DVec3) -> f64 {
+    let conic = Conic::from_state(mu, position, velocity);
+    let e = conic.eccentricity;
+    let h = position.cross(velocity);
+    let e_vec = velocity.cross(h) / mu - position.normalize_or_zero();
+    let true_anomaly = if e < 1e-12 {
+        0.0
+    } else {
+        let cos_nu = (e_vec.dot(position) / (e * position.length())).clamp(-1.0, 1.0);
+        cos_nu.acos().copysign(position.dot(velocity))
+    };
+    let half = (true_anomaly / 2.0).tan();
+    let mean_anomaly = if conic.is_parabolic() {
+        half + half.powi(3) / 3.0
+    } else if e < 1.0 {
+        let E = 2.0 * (((1.0 - e) / (1.0 + e)).sqrt() * half).atan();
+        E - e * E.sin()
+    } else {
+        let H = 2.0 * (((e - 1.0) / (e + 1.0)).sqrt() * half).atanh();
+        e * H.sinh() - H
+    };
+    mean_anomaly / conic.mean_motion(mu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn kepler_equation_converges() {
+        for e in [0.0, 0.1, 0.5, 0.9, 0.99, 0.999] {
+            for m in [-20.0, -3.1, -0.5, 0.0, 1e-6, 0.3, 2.0, PI - 1e-9, 100.0] {
+                let E = eccentric_anomaly(m, e);
+                let residual = (E - e * E.sin() - m).rem_euclid(TAU);
+                assert!(
+                    residual.min(TAU - residual) < 1e-12,
+                    "M = {m}, e = {e}: residual {residual}"
+                );
+            }
+        }
+    }
+
+    /// Energy (m²/s²) and angular momentum (m²/s) per unit mass.
+    fn invariants(mu: f64, (position, velocity): (DVec3, DVec3)) -> (f64, DVec3) {
+        (
+            velocity.length_squared() / 2.0 - mu / position.length(),
+            position.cross(velocity),
+        )
+    }
+
+    #[test]
+    fn open_orbits_conserve_invariants() {
+        let mu = 3.986e14;
+        for e in [1.0, 1.0 + 1e-7, 1.0 - 1e-7, 1.5, 4.0] {
+            let conic = Conic {
+                periapsis: 7.0e6,
+                eccentricity: e,
+            };
+            let start = conic.periapsis_state(mu);
+            let (energy, momentum) = invariants(mu, start);
+            for dt in [-86_400.0, -60.0, 1.0, 3_600.0, 1.0e6] {
+                let state = propagate(mu, start.0, start.1, dt);
+                let (e2, h2) = invariants(mu, state);
+                assert!(
+                    (e2 - energy).abs() < 1e-6 * (mu / conic.periapsis),
+                    "e = {e}, dt = {dt}: energy {energy} -> {e2}"
+                );
+                assert!(
+                    (h2 - momentum).length() < 1e-9 * momentum.length(),
+                    "e = {e}, dt = {dt}: momentum {momentum} -> {h2}"
+                );
+                let elapsed = time_since_periapsis(mu, state.0, state.1);
+                if e >= 1.0 {
+                    assert!(
+                        (elapsed - dt).abs() < 1e-6 * dt.abs().max(1.0),
+                        "e = {e}: {elapsed} s since periapsis, expected {dt} s"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn universal_variables_match_ellipse() {
+        let (mu, a, e) = (3.986e14, 2.4e7, 0.7);
+        let conic = Conic {
+            periapsis: a * (1.0 - e),
+            eccentricity: e,
+        };
+        for m in [-2.0, 0.4, 3.0] {
+            let (p1, v1) = perifocal_state(a, e, mu, m);
+            let (p2, v2) = conic.perifocal_state(mu, m);
+            assert!((p1 - p2).length() < 1e-3, "{p1} vs {p2}");
+            assert!((v1 - v2).length() < 1e-6, "{v1} vs {v2}");
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+
+use bevy::math::DVec3;
+use hifitime::{Duration, Epoch};
+use serde::{Deserialize, Deserializer, Serialize};
+use smol_str::SmolStr;
+
+use crate::orrery::orrery_cfg::de_time;
+
+/// Weights of the sixth-order symplectic composition of leapfrog steps (Yoshida 1990, solution A).
+const YOSHIDA6: [f64; 7] = {
+    const W1: f64 = -1.177_679_984_178_87;
+    const W2: f64 = 0.235_573_213_359_357;
+    const W3: f64 = 0.784_513_610_477_560;
+    const W0: f64 = 1.0 - 2.0 * (W1 + W2 + W3);
+    [W3, W2, W1, W0, W1, W2, W3]
+};
+
+/// Settings of the N-body propagation.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct NBodyCfg {
+    /// Interval (s) between the states kept in the ephemeris.
+    #[serde(deserialize_with = "de_time", default = "default_step")]
+    pub step: f64,
+    /// Integrator steps per ephemeris interval.
+    #[serde(deserialize_with = "de_positive", default = "default_substeps")]
+    pub substeps: u32,
+    /// How far (s) ahead of the simulation the ephemeris is kept.
+    #[serde(deserialize_with = "de_time", default = "default_lookahead")]
+    pub lookahead: f64,
+}
+
+fn default_step() -> f64 {
+    600.0
+}
+
+fn default_substeps() -> u32 {
+    10
+}
+
+fn default_lookahead() -> f64 {
+    86_400.0
+}
+
+fn de_positive<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = u32::deserialize(deserializer)?;
+    if value == 0 {
+        return Err(serde::de::Error::custom("must be at least 1"));
+    }
+    Ok(value)
+}
+
+/// Position (m) and velocity (m/s) of a body in the inertial frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct State {
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+/// States of every body, integrated under their mutual gravity and sampled at regular intervals from a start epoch.
+pub struct Ephemeris {
+    cfg: NBodyCfg,
+    start: Epoch,
+    /// Index of each body's state.
+    index: BTreeMap<SmolStr, usize>,
+    /// Gravitational parameters (m³/s²), in the order of the states.
+    mu: Vec<f64>,
+    /// One set of states per interval since the start.
+    samples: Vec<Vec<State>>,
+}
+
+impl Ephemeris {
+    /// Starts an ephemeris from the gravitational parameter (m³/s²) and state of each body at an epoch.
+    pub fn new(
+        cfg: NBodyCfg,
+        start: Epoch,
+        bodies: impl IntoIterator<Item = (SmolStr, f64, State)>,
+    ) -> Self {
+        let mut index = BTreeMap::new();
+        let mut mu = vec![];
+        let mut initial = vec![];
+        for (name, body_mu, state) in bodies {
+            index.insert(name, mu.len());
+            mu.push(body_mu);
+            initial.push(state);
+        }
+        Self {
+            cfg,
+            start,
+            index,
+            mu,
+            samples: vec![initial],
+        }
+    }
+
+    pub fn cfg(&self) -> &NBodyCfg {
+        &self.cfg
+    }
+
+    /// The last epoch covered by the ephemeris.
+    pub fn end(&self) -> Epoch {
+        self.start + Duration::from_seconds((self.samples.len() - 1) as f64 * self.cfg.step)
+    }
+
+    /// Integrates forward until the ephemeris covers an epoch.
+    pub fn extend_to(&mut self, epoch: Epoch) {
+        let h = self.cfg.step / self.cfg.substeps as f64;
+        while self.end() < epoch {
+            let mut states = self.samples.last().unwrap().clone();
+            for _ in 0..self.cfg.substeps {
+                for weight in YOSHIDA6 {
+                    self.leapfrog(&mut states, weight * h);
+                }
+            }
+            self.samples.push(states);
+        }
+    }
+
+    /// Interpolates the state of a body, or returns None if the body or the epoch isn't covered.
+    pub fn state(&self, body: &str, epoch: Epoch) -> Option<State> {
+        let index = *self.index.get(body)?;
+        let t = (epoch - self.start).to_seconds() / self.cfg.step;
+        let last = self.samples.len() - 1;
+        if t < 0.0 || t > last as f64 {
+            return None;
+        }
+        let k = (t.floor() as usize).min(last.saturating_sub(1));
+        let a = &self.samples[k][index];
+        if k == last {
+            return Some(*a);
+        }
+        Some(hermite(
+            a,
+            &self.samples[k + 1][index],
+            t - k as f64,
+            self.cfg.step,
+        ))
+    }
+
+    /// A kick–drift–kick leapfrog step.
+    fn leapfrog(&self, states: &mut [State], h: f64) {
+        let acc = self.accelerations(states);
+        for (state, acc) in states.iter_mut().zip(&acc) {
+            state.velocity += acc * h / 2.0;
+            state.position += state.velocity * h;
+        }
+        let acc = self.accelerations(states);
+        for (state, acc) in states.iter_mut().zip(&acc) {
+            state.velocity += acc * h / 2.0;
+        }
+    }
+
+    fn accelerations(&self, states: &[State]) -> Vec<DVec3> {
+        let mut acc = vec![DVec3::ZERO; states.len()];
+        for i in 0..states.len() {
+            for j in i + 1..states.len() {
+                let d = states[j].position - states[i].position;
+                let r2 = d.length_squared();
+                if r2 == 0.0 {
+                    continue;
+                }
+                let d = d / (r2 * r2.sqrt());
+                acc[i] += d * self.mu[j];
+                acc[j] -= d * self.mu[i];
+            }
+        }
+        acc
+    }
+}
+
+/// Cubic Hermite interpolation between two states an interval `h` (s) apart, at a fraction `s` of the interval.
+fn hermite(a: &State, b: &State, s: f64, h: f64) -> State {
+    let (s2, s3) = (s * s, s * s * s);
+    let position = a.position * (2.0 * s3 - 3.0 * s2 + 1.0)
+        + a.velocity * h * (s3 - 2.0 * s2 + s)
+        + b.position * (-2.0 * s3 + 3.0 * s2)
+        + b.velocity * h * (s3 - s2);
+    let velocity = (a.position * (6.0 * s2 - 6.0 * s)
+        + a.velocity * h * (3.0 * s2 - 4.0 * s + 1.0)
+        + b.position * (-6.0 * s2 + 6.0 * s)
+        + b.velocity * h * (3.0 * s2 - 2.0 * s))
+        / h;
+    State { position, velocity }
+}
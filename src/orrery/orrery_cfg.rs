@@ -1,16 +1,70 @@
-
 use serde::{Deserialize, Deserializer, Serialize};
 use smol_str::SmolStr;
 
 use bevy::asset::Asset;
 use bevy::reflect::TypePath;
 
+use crate::orrery::harmonics::ZonalHarmonics;
+use crate::orrery::nbody::NBodyCfg;
+use crate::physics::aerodynamics::AtmosphereCfg;
+
 #[derive(Asset, TypePath, Clone, Debug, Serialize, Deserialize)]
 pub struct OrreryCfg {
     pub name: SmolStr,
+    /// Unit of angles given as bare numbers.
+    #[serde(default)]
+    pub angle_unit: AngleUnit,
+    /// How the bodies move along their orbits.
+    #[serde(default)]
+    pub propagation: Propagation,
+    /// Whether parents are displaced by their children, each orbit then being that of a body and its descendants
+    /// about their common barycentre, with the barycentre of the whole system at the origin.
+    #[serde(default)]
+    pub barycentric: bool,
     pub bodies: Vec<Body>,
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case", tag = "mode")]
+pub enum Propagation {
+    /// Each body follows a fixed conic around its parent.
+    #[default]
+    Kepler,
+    /// Bodies start on their conics, then move under the gravity of every other body.
+    NBody(NBodyCfg),
+}
+
+impl OrreryCfg {
+    /// Converts every angle given as a bare number to radians, according to the file's `angle_unit`.
+    pub fn resolve_angles(&mut self) {
+        let unit = self.angle_unit;
+        for body in &mut self.bodies {
+            let Orbit {
+                inclination,
+                ascending_node,
+                arg_of_pericenter,
+                mean_anomaly,
+                ..
+            } = &mut body.orbit;
+            let Rotation {
+                obliquity,
+                eq_ascend_node,
+                ..
+            } = &mut body.rotation;
+            for angle in [
+                inclination,
+                ascending_node,
+                arg_of_pericenter,
+                mean_anomaly,
+                obliquity,
+                eq_ascend_node,
+            ] {
+                angle.resolve(unit);
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Body {
     pub name: SmolStr,
@@ -25,8 +79,19 @@ pub struct Body {
 
     #[serde(deserialize_with = "de_mass", default)]
     pub mass: f64,
+    /// Gravitational parameter GM (m³/s²), which governs every orbit around the body. Derived from `mass` if not
+    /// given, and the other way around.
+    #[serde(default)]
+    pub mu: f64,
     #[serde(deserialize_with = "de_distance", default)]
     pub radius: f64,
+    /// Departure of the gravity field from a point mass, relative to `radius`.
+    #[serde(flatten)]
+    pub harmonics: ZonalHarmonics,
+
+    /// Bodies without an atmosphere are surrounded by vacuum.
+    #[serde(default)]
+    pub atmosphere: Option<AtmosphereCfg>,
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Default)]
@@ -41,20 +106,27 @@ pub enum BodyClass {
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, Default)]
 pub struct Orbit {
+    /// Negative for hyperbolic orbits, and unused for parabolic ones.
     #[serde(deserialize_with = "de_distance", default)]
     pub semi_major: f64,
+    /// Distance of the periapsis from the parent, which may be given instead of the semi-major axis and is the only
+    /// way to size a parabolic orbit.
+    #[serde(deserialize_with = "de_distance", default)]
+    pub periapsis: f64,
     #[serde(deserialize_with = "de_time", default)]
     pub period: f64,
     #[serde(default)]
     pub eccentricity: f64,
-    #[serde(default)]
-    pub inclination: f64,
-    #[serde(default)]
-    pub ascending_node: f64,
-    #[serde(default)]
-    pub arg_of_pericenter: f64,
-    #[serde(default)]
-    pub mean_anomaly: f64,
+    #[serde(deserialize_with = "de_angle", default)]
+    pub inclination: Angle,
+    #[serde(deserialize_with = "de_angle", default)]
+    pub ascending_node: Angle,
+    #[serde(deserialize_with = "de_angle", default)]
+    pub arg_of_pericenter: Angle,
+    /// Measured from periapsis; on open orbits, it's the hyperbolic mean anomaly e sinh H − H, or for a parabola
+    /// the D + D³/3 of Barker's equation.
+    #[serde(deserialize_with = "de_angle", default)]
+    pub mean_anomaly: Angle,
     #[serde(default)]
     pub epoch: f64,
 }
@@ -63,14 +135,123 @@ pub struct Orbit {
 pub struct Rotation {
     #[serde(deserialize_with = "de_time", default)]
     pub rotation_period: f64,
-    #[serde(default)]
-    pub obliquity: f64,
-    #[serde(default)]
-    pub eq_ascend_node: f64,
+    #[serde(deserialize_with = "de_angle", default)]
+    pub obliquity: Angle,
+    #[serde(deserialize_with = "de_angle", default)]
+    pub eq_ascend_node: Angle,
     #[serde(default)]
     pub rotation_epoch: f64,
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AngleUnit {
+    #[default]
+    #[serde(alias = "deg")]
+    Degrees,
+    #[serde(alias = "rad")]
+    Radians,
+}
+
+impl AngleUnit {
+    fn to_radians(self, value: f64) -> f64 {
+        match self {
+            AngleUnit::Degrees => value.to_radians(),
+            AngleUnit::Radians => value,
+        }
+    }
+}
+
+/// An angle from the configuration.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Angle {
+    /// A bare number, in the unit of the file it came from.
+    Bare(f64),
+    Radians(f64),
+}
+
+impl Default for Angle {
+    fn default() -> Self {
+        Angle::Radians(0.0)
+    }
+}
+
+impl Angle {
+    /// Gives a bare number the unit of its file.
+    pub fn resolve(&mut self, unit: AngleUnit) {
+        if let Angle::Bare(value) = *self {
+            *self = Angle::Radians(unit.to_radians(value));
+        }
+    }
+
+    /// The angle in radians. Bare numbers must have been resolved first; in release builds, those that weren't are
+    /// taken in the default unit, degrees.
+    pub fn rad(self) -> f64 {
+        debug_assert!(
+            matches!(self, Angle::Radians(_)),
+            "{self:?} was never given a unit"
+        );
+        match self {
+            Angle::Bare(value) => AngleUnit::default().to_radians(value),
+            Angle::Radians(value) => value,
+        }
+    }
+}
+
+pub(crate) fn de_angle<'de, D>(deserializer: D) -> Result<Angle, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct AngleVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for AngleVisitor {
+        type Value = Angle;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str(
+                r#"a number (in the file's angle unit) or a string like "7.0 deg", "0.12 rad""#,
+            )
+        }
+
+        // ---------- numeric literals (in the file's angle unit) ----------
+
+        fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+            Ok(Angle::Bare(v))
+        }
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(Angle::Bare(v as f64))
+        }
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(Angle::Bare(v as f64))
+        }
+
+        // ---------- strings with optional unit ----------
+
+        fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            let mut parts = s.split_whitespace();
+            let value: f64 = parts
+                .next()
+                .ok_or_else(|| E::custom("missing value"))?
+                .parse()
+                .map_err(E::custom)?;
+
+            let unit = match parts.next().unwrap_or("").to_ascii_lowercase().as_str() {
+                "" => return Ok(Angle::Bare(value)),
+                "deg" | "degree" | "degrees" | "°" => AngleUnit::Degrees,
+                "rad" | "radian" | "radians" => AngleUnit::Radians,
+                other => return Err(E::custom(format!("unknown angle unit: {other}"))),
+            };
+
+            Ok(Angle::Radians(unit.to_radians(value)))
+        }
+    }
+
+    deserializer.deserialize_any(AngleVisitor)
+}
+
 fn de_mass<'de, D>(deserializer: D) -> Result<f64, D::Error>
 where
     D: Deserializer<'de>,
@@ -116,7 +297,7 @@ where
     deserializer.deserialize_any(MassVisitor)
 }
 
-fn de_distance<'de, D>(deserializer: D) -> Result<f64, D::Error>
+pub(crate) fn de_distance<'de, D>(deserializer: D) -> Result<f64, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -139,6 +320,9 @@ where
         fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
             Ok(v as f64)
         }
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(v as f64)
+        }
 
         // ---------- strings with optional unit ----------
 
@@ -173,7 +357,7 @@ where
     deserializer.deserialize_any(DistanceVisitor)
 }
 
-fn de_time<'de, D>(deserializer: D) -> Result<f64, D::Error>
+pub(crate) fn de_time<'de, D>(deserializer: D) -> Result<f64, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -212,12 +396,14 @@ where
             // default to seconds if no unit supplied
             let unit = parts.next().unwrap_or("").to_ascii_lowercase();
 
+            const SEC_PER_MINUTE: f64 = 60.0;
             const SEC_PER_HOUR: f64 = 3_600.0;
             const SEC_PER_DAY: f64 = 86_400.0;
             const SEC_PER_YEAR: f64 = 31_557_600.0; // 365.25 d (Julian year)
 
             let factor = match unit.as_str() {
                 "" | "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+                "min" | "mins" | "minute" | "minutes" => SEC_PER_MINUTE,
                 "h" | "hr" | "hrs" | "hour" | "hours" => SEC_PER_HOUR,
                 "d" | "day" | "days" => SEC_PER_DAY,
                 "yr" | "year" | "years" => SEC_PER_YEAR,
@@ -1,68 +1,229 @@
 use std::collections::BTreeMap;
 
-use crate::precision::ToMillimetersExt;
+use anyhow::Context;
+
+use crate::precision::{ToMetersExt, ToMillimetersExt};
 use bevy::{
     ecs::resource::Resource,
+    log::warn,
     math::{DQuat, DVec3, I64Vec3},
 };
-use hifitime::Epoch;
+use hifitime::{Duration, Epoch};
 use smol_str::SmolStr;
 use std::f64::consts::PI;
 
-use crate::orrery::orrery_cfg::{Body, OrreryCfg};
+use crate::orrery::kepler::{self, Conic};
+use crate::orrery::nbody::{Ephemeris, State};
+use crate::orrery::orrery_cfg::{Body, Orbit, OrreryCfg, Propagation};
+use crate::physics::aerodynamics::BodyAtmosphere;
+
+/// Gravitational constant [m^3 kg^-1 s^-2]
+const G: f64 = 6.674e-11;
 
 /// A solver for a whole star system
 #[derive(Resource)]
 pub struct Orrery {
-    name: SmolStr,
     bodies: BTreeMap<SmolStr, Body>,
+    atmospheres: BTreeMap<SmolStr, BodyAtmosphere>,
+    /// Names of the bodies, each after its parent.
+    order: Vec<SmolStr>,
+    /// Whether parents are displaced by their children.
+    barycentric: bool,
+    /// Names of the children of each body.
+    children: BTreeMap<SmolStr, Vec<SmolStr>>,
+    /// Gravitational parameter (m³/s²) of each body together with all its descendants.
+    system_mu: BTreeMap<SmolStr, f64>,
+    /// States integrated under mutual gravity, when the system isn't purely Keplerian.
+    ephemeris: Option<Ephemeris>,
+    /// The state of every body at the epoch of the current tick.
+    cache: Option<Snapshot>,
+}
+
+/// The state of every body at one epoch.
+struct Snapshot {
+    epoch: Epoch,
+    bodies: BTreeMap<SmolStr, CachedBody>,
+}
+
+struct CachedBody {
+    /// Position (mm), in the inertial frame.
+    position: I64Vec3,
+    /// Velocity (m/s) relative to the parent.
+    velocity: DVec3,
+    rotation: DQuat,
 }
 
 impl Orrery {
-    /// Create a new star-system solver.
-    pub fn init(cfg: OrreryCfg) -> anyhow::Result<Self> {
+    /// Create a new star-system solver, for a simulation that starts at `start`.
+    pub fn init(mut cfg: OrreryCfg, start: Epoch) -> anyhow::Result<Self> {
+        cfg.resolve_angles();
         let mut bodies: BTreeMap<SmolStr, Body> = BTreeMap::new();
+        let mut atmospheres = BTreeMap::new();
+        let mut order = vec![];
         for mut body in cfg.bodies {
             let name = body.name.clone();
+            order.push(name.clone());
             // ensure parent exists before computing period
             if let Some(parent) = body.parent.as_ref()
                 && !bodies.contains_key(parent)
             {
                 anyhow::bail!("unidentified parent {parent} of {name}");
             }
-            // calculate missing orbital period via Kepler's third law if semi-major axis is non-zero
-            if body.orbit.period == 0.0 && body.orbit.semi_major != 0.0 {
-                // gravitational constant [m^3 kg^-1 s^-2]
-                const G: f64 = 6.674e-11;
-                // semi-major axis is in meters
-                let a_m = body.orbit.semi_major;
-                // parent mass in kg if any
-                let parent_mass = if let Some(parent_name) = &body.parent {
-                    if let Some(parent) = bodies.get(parent_name) {
-                        parent.mass
-                    } else {
-                        0.0
-                    }
-                } else {
+            if body.mu == 0.0 {
+                body.mu = G * body.mass;
+            } else if body.mass == 0.0 {
+                body.mass = body.mu / G;
+            }
+            // the period follows from the gravitational parameters, by Kepler's third law: T = 2π √(a³ / μ)
+            let parent_mu = body
+                .parent
+                .as_ref()
+                .and_then(|parent| bodies.get(parent))
+                .map_or(0.0, |parent| parent.mu);
+            let mu = parent_mu + body.mu;
+            let orbit = &mut body.orbit;
+            let conic = Conic {
+                periapsis: orbit.periapsis,
+                eccentricity: orbit.eccentricity,
+            };
+            if orbit.periapsis != 0.0 {
+                let semi_major = if conic.is_parabolic() {
                     0.0
+                } else {
+                    conic.semi_major()
                 };
-                // Kepler's third law: T = 2π * sqrt(a^3 / (G (M_parent + M_body)))
-                let mu = G * (parent_mass + body.mass);
-                body.orbit.period = 2.0 * std::f64::consts::PI * (a_m.powi(3) / mu).sqrt();
+                if orbit.semi_major != 0.0 && (orbit.semi_major / semi_major - 1.0).abs() > 1e-3 {
+                    warn!(
+                        "semi-major axis of {name} ({} m) disagrees with its periapsis; using {semi_major} m",
+                        orbit.semi_major
+                    );
+                }
+                orbit.semi_major = semi_major;
+            } else if orbit.semi_major != 0.0 {
+                if conic.is_parabolic() {
+                    anyhow::bail!("parabolic orbit of {name} needs a periapsis");
+                }
+                orbit.periapsis = orbit.semi_major * (1.0 - orbit.eccentricity);
+            }
+            if orbit.periapsis < 0.0 {
+                anyhow::bail!("orbit of {name} has a negative periapsis");
+            }
+            // parabolas and hyperbolas have no period, and are followed from their periapsis instead
+            if orbit.periapsis != 0.0 && (orbit.semi_major <= 0.0 || conic.is_parabolic()) {
+                if mu <= 0.0 {
+                    anyhow::bail!("open orbit of {name} needs a parent with a mass");
+                }
+                orbit.period = 0.0;
+            } else if orbit.semi_major != 0.0 && mu > 0.0 {
+                let period = 2.0 * PI * (orbit.semi_major.powi(3) / mu).sqrt();
+                if orbit.period != 0.0 && (orbit.period / period - 1.0).abs() > 1e-3 {
+                    warn!(
+                        "period of {name} ({} s) disagrees with its gravitational parameters; using {period} s",
+                        orbit.period
+                    );
+                }
+                orbit.period = period;
+            }
+            if let Some(atmosphere) = &body.atmosphere {
+                let datum_radius = body.radius + atmosphere.datum;
+                let gravity = body.mu / (datum_radius * datum_radius);
+                let atmosphere = BodyAtmosphere::new(atmosphere, gravity)
+                    .with_context(|| format!("bad atmosphere of {name}"))?;
+                atmospheres.insert(name.clone(), atmosphere);
             }
             if bodies.insert(name.clone(), body).is_some() {
                 anyhow::bail!("duplicate name in star system: {name}");
             }
         }
-        Ok(Self {
-            name: cfg.name,
+        let mut children = BTreeMap::<SmolStr, Vec<SmolStr>>::new();
+        let mut system_mu = BTreeMap::new();
+        // children come after their parents, so they are totalled first in reverse
+        for name in order.iter().rev() {
+            let body = &bodies[name];
+            let mu = body.mu + system_mu.get(name).copied().unwrap_or(0.0);
+            system_mu.insert(name.clone(), mu);
+            if let Some(parent) = &body.parent {
+                *system_mu.entry(parent.clone()).or_insert(0.0) += mu;
+                children
+                    .entry(parent.clone())
+                    .or_default()
+                    .insert(0, name.clone());
+            }
+        }
+        let mut orrery = Self {
             bodies,
-        })
+            atmospheres,
+            order,
+            barycentric: cfg.barycentric,
+            children,
+            system_mu,
+            ephemeris: None,
+            cache: None,
+        };
+        if let Propagation::NBody(nbody) = cfg.propagation {
+            // the integration starts from the conics at the start of the simulation
+            let states = orrery
+                .bodies
+                .values()
+                .map(|body| {
+                    let state = State {
+                        position: orrery.kepler_position(&body.name, start)?.to_meters_64(),
+                        velocity: orrery.kepler_absolute_velocity(&body.name, start)?,
+                    };
+                    Some((body.name.clone(), body.mu, state))
+                })
+                .collect::<Option<Vec<_>>>()
+                .context("cannot start the N-body propagation")?;
+            let mut ephemeris = Ephemeris::new(nbody, start, states);
+            ephemeris.extend_to(start + Duration::from_seconds(nbody.lookahead));
+            orrery.ephemeris = Some(ephemeris);
+        }
+        Ok(orrery)
+    }
+
+    /// Moves the system on to the epoch of a new tick: keeps the ephemeris, if any, ahead of it, and caches the state
+    /// of every body at it.
+    pub fn advance(&mut self, epoch: Epoch) {
+        if let Some(ephemeris) = &mut self.ephemeris {
+            let lookahead = Duration::from_seconds(ephemeris.cfg().lookahead);
+            ephemeris.extend_to(epoch + lookahead);
+        }
+        let mut bodies = BTreeMap::<SmolStr, CachedBody>::new();
+        for name in &self.order {
+            let cfg = &self.bodies[name];
+            let (position, velocity) =
+                match self.ephemeris.as_ref().and_then(|e| e.state(name, epoch)) {
+                    Some(state) => (
+                        state.position.to_millimeters(),
+                        self.ephemeris_velocity(cfg, epoch)
+                            .unwrap_or(state.velocity),
+                    ),
+                    None => {
+                        // parents come first, so each body only solves its own conic
+                        let (position, velocity) = self.relative_state(cfg, epoch);
+                        let parent = cfg
+                            .parent
+                            .as_ref()
+                            .map_or(I64Vec3::ZERO, |p| bodies[p].position);
+                        (parent + position.to_millimeters(), velocity)
+                    }
+                };
+            let rotation = Self::rotation(cfg, epoch);
+            bodies.insert(
+                name.clone(),
+                CachedBody {
+                    position,
+                    velocity,
+                    rotation,
+                },
+            );
+        }
+        self.cache = Some(Snapshot { epoch, bodies });
     }
 
-    /// Iterates through the bodies of the system.
+    /// Iterates through the bodies of the system, in the order of its configuration, each after its parent.
     pub fn iter(&self) -> impl Iterator<Item = &Body> {
-        self.bodies.values()
+        self.order.iter().map(|name| &self.bodies[name])
     }
 
     /// Gets a body by name.
@@ -70,116 +231,260 @@ impl Orrery {
         self.bodies.get(name)
     }
 
+    /// Gets the atmosphere of a body, if it has one.
+    pub fn get_atmosphere(&self, name: &str) -> Option<&BodyAtmosphere> {
+        self.atmospheres.get(name)
+    }
+
+    /// Gravitational acceleration (m/s²) at a distance (m) from the centre of a body. Returns None if such a body
+    /// does not exist in the system.
+    pub fn gravity_at(&self, body: &str, distance: f64) -> Option<f64> {
+        let body = self.bodies.get(body)?;
+        Some(body.mu / (distance * distance).max(1.0))
+    }
+
+    /// Solves for the gravitational acceleration (m/s²) towards a body, including its zonal harmonics, at an offset
+    /// (m) from the body's centre in the inertial frame.
+    pub fn solve_gravity(&self, body: &str, offset: DVec3, epoch: Epoch) -> Option<DVec3> {
+        let cfg = self.bodies.get(body)?;
+        let r2 = offset.length_squared().max(1.0);
+        let point_mass = -offset.normalize_or_zero() * cfg.mu / r2;
+        Some(point_mass + self.solve_zonal_acceleration(body, offset, epoch)?)
+    }
+
+    /// Solves for the acceleration (m/s²) due to a body's zonal harmonics, on top of its point-mass gravity, at an
+    /// offset (m) from the body's centre in the inertial frame.
+    pub fn solve_zonal_acceleration(
+        &self,
+        body: &str,
+        offset: DVec3,
+        epoch: Epoch,
+    ) -> Option<DVec3> {
+        let cfg = self.bodies.get(body)?;
+        if cfg.harmonics.is_zero() {
+            return Some(DVec3::ZERO);
+        }
+        // the harmonics are defined in the body-fixed frame
+        let rotation = self.solve_rotation(body, epoch)?;
+        let acc = cfg
+            .harmonics
+            .acceleration(cfg.mu, cfg.radius, rotation.inverse() * offset);
+        Some(rotation * acc)
+    }
+
+    /// Rotation from a body's orbital plane, with the pericenter along X, to the inertial frame. Around an oblate
+    /// parent, the orbit's node regresses about the parent's spin axis and its pericenter advances.
+    fn orbit_frame(&self, cfg: &Body, epoch: Epoch) -> DQuat {
+        let rot = DQuat::from_rotation_z(cfg.orbit.ascending_node.rad())
+            * DQuat::from_rotation_x(cfg.orbit.inclination.rad())
+            * DQuat::from_rotation_z(cfg.orbit.arg_of_pericenter.rad());
+        let Some(parent) = cfg.parent.as_ref().and_then(|p| self.bodies.get(p)) else {
+            return rot;
+        };
+        if parent.harmonics.j2 == 0.0 || cfg.orbit.period == 0.0 {
+            return rot;
+        }
+        let spin_axis = self.solve_rotation(&parent.name, epoch).unwrap_or_default() * DVec3::Z;
+        let cos_i = (rot * DVec3::Z).dot(spin_axis);
+        let e = cfg.orbit.eccentricity;
+        let (node_rate, pericenter_rate) = parent.harmonics.secular_rates(
+            parent.radius,
+            2.0 * PI / cfg.orbit.period,
+            cfg.orbit.semi_major * (1.0 - e * e),
+            cos_i,
+        );
+        let dt = (epoch - Epoch::from_mjd_utc(cfg.orbit.epoch)).to_seconds();
+        DQuat::from_axis_angle(spin_axis, node_rate * dt)
+            * rot
+            * DQuat::from_rotation_z(pericenter_rate * dt)
+    }
+
     /// Solves for the position, in millimeters, of a particular body in the system, at a particular time. Returns None if such a body does not exist in the system.
-    #[allow(non_snake_case)]
     pub fn solve_position(&self, body: &str, epoch: Epoch) -> Option<I64Vec3> {
-        // Lookup body and compute parent position
-        let body_cfg = self.bodies.get(body)?;
-        let parent_pos = if let Some(parent) = &body_cfg.parent {
-            self.solve_position(parent, epoch)?
-        } else {
-            I64Vec3::ZERO
-        };
-        // Bodies with zero semi-major axis are fixed relative to their parent
-        if body_cfg.orbit.semi_major == 0.0 {
-            return Some(parent_pos);
+        if let Some(cached) = self.cached(body, epoch) {
+            return Some(cached.position);
+        }
+        if let Some(state) = self.ephemeris.as_ref().and_then(|e| e.state(body, epoch)) {
+            return Some(state.position.to_millimeters());
+        }
+        self.kepler_position(body, epoch)
+    }
+
+    /// Solves for the orbital velocity (m/s) of a body relative to its parent at a given time, in inertial frame.
+    /// Returns None if the body is not found.
+    pub fn solve_velocity(&self, body: &str, epoch: Epoch) -> Option<DVec3> {
+        if let Some(cached) = self.cached(body, epoch) {
+            return Some(cached.velocity);
         }
+        let cfg = self.bodies.get(body)?;
+        if let Some(velocity) = self.ephemeris_velocity(cfg, epoch) {
+            return Some(velocity);
+        }
+        Some(self.relative_state(cfg, epoch).1)
+    }
 
-        // Time since reference epoch (config epoch is in MJD)
-        let epoch0 = Epoch::from_mjd_utc(body_cfg.orbit.epoch);
-        let dt_s = (epoch - epoch0).to_seconds();
+    /// Solves for the velocity (m/s) of a body in the inertial frame, including that of its parents. Returns None if
+    /// the body is not found.
+    pub fn solve_absolute_velocity(&self, body: &str, epoch: Epoch) -> Option<DVec3> {
+        let mut velocity = DVec3::ZERO;
+        let mut next = Some(body);
+        while let Some(name) = next {
+            velocity += self.solve_velocity(name, epoch)?;
+            next = self.bodies.get(name)?.parent.as_deref();
+        }
+        Some(velocity)
+    }
 
-        // Mean anomaly at current epoch
-        let n = 2.0 * std::f64::consts::PI / body_cfg.orbit.period;
-        let m = body_cfg.orbit.mean_anomaly + n * dt_s;
+    /// The velocity (m/s) of a body relative to its parent, from the ephemeris if it covers the epoch.
+    fn ephemeris_velocity(&self, cfg: &Body, epoch: Epoch) -> Option<DVec3> {
+        let ephemeris = self.ephemeris.as_ref()?;
+        let state = ephemeris.state(&cfg.name, epoch)?;
+        let parent_velocity = cfg
+            .parent
+            .as_ref()
+            .and_then(|parent| ephemeris.state(parent, epoch))
+            .map_or(DVec3::ZERO, |parent| parent.velocity);
+        Some(state.velocity - parent_velocity)
+    }
 
-        // Solve Kepler's equation for eccentric anomaly E via Newton's method
-        let e = body_cfg.orbit.eccentricity;
-        let mut E = m;
-        for _ in 0..50 {
-            let f = E - e * E.sin() - m;
-            let f_prime = 1.0 - e * E.cos();
-            E -= f / f_prime;
+    /// The velocity (m/s) of a body on its conic, including that of its parents.
+    fn kepler_absolute_velocity(&self, body: &str, epoch: Epoch) -> Option<DVec3> {
+        let mut velocity = DVec3::ZERO;
+        let mut next = Some(body);
+        while let Some(name) = next {
+            let cfg = self.bodies.get(name)?;
+            velocity += self.relative_state(cfg, epoch).1;
+            next = cfg.parent.as_deref();
         }
+        Some(velocity)
+    }
 
-        // True anomaly
-        let cos_E = E.cos();
-        let sin_E = E.sin();
-        let v = ((1.0 - e * e).sqrt() * sin_E).atan2(cos_E - e);
+    /// The position (mm) of a body on its conic, including the positions of its parents.
+    fn kepler_position(&self, body: &str, epoch: Epoch) -> Option<I64Vec3> {
+        let mut position = I64Vec3::ZERO;
+        let mut next = Some(body);
+        while let Some(name) = next {
+            let cfg = self.bodies.get(name)?;
+            position += self.relative_state(cfg, epoch).0.to_millimeters();
+            next = cfg.parent.as_deref();
+        }
+        Some(position)
+    }
 
-        // Radius in orbital plane (m)
-        let r_m = body_cfg.orbit.semi_major * (1.0 - e * cos_E);
+    /// The position (m) and velocity (m/s) of a body's centre relative to its parent's centre, when following conics.
+    fn relative_state(&self, cfg: &Body, epoch: Epoch) -> (DVec3, DVec3) {
+        let (position, velocity) = self.kepler_state(cfg, epoch);
+        let (offset, drift) = self.barycentric_offset(cfg, epoch);
+        (position - offset, velocity - drift)
+    }
 
-        // Position in orbital plane (m)
-        let pos_orb = DVec3::new(r_m * v.cos(), r_m * v.sin(), 0.0);
+    /// Position (m) and velocity (m/s) of the barycentre of a body and its descendants relative to the body's centre.
+    /// In a barycentric system, the conics are those of these barycentres, around the centre of the parent; otherwise
+    /// this is zero.
+    fn barycentric_offset(&self, cfg: &Body, epoch: Epoch) -> (DVec3, DVec3) {
+        let total = self.system_mu.get(&cfg.name).copied().unwrap_or(0.0);
+        if !self.barycentric || total <= 0.0 {
+            return (DVec3::ZERO, DVec3::ZERO);
+        }
+        let mut offset = (DVec3::ZERO, DVec3::ZERO);
+        for child in self.children.get(&cfg.name).into_iter().flatten() {
+            let weight = self.system_mu[child] / total;
+            let (position, velocity) = self.kepler_state(&self.bodies[child], epoch);
+            offset.0 += position * weight;
+            offset.1 += velocity * weight;
+        }
+        offset
+    }
 
-        // Rotate from orbital plane to inertial frame
-        let rot = DQuat::from_rotation_z(body_cfg.orbit.ascending_node)
-            * DQuat::from_rotation_x(body_cfg.orbit.inclination)
-            * DQuat::from_rotation_z(body_cfg.orbit.arg_of_pericenter);
-        let pos_inertial = rot * pos_orb;
+    /// The position (m) and velocity (m/s) of a body on its conic around its parent. Bodies without a periapsis are
+    /// fixed to their parent.
+    fn kepler_state(&self, cfg: &Body, epoch: Epoch) -> (DVec3, DVec3) {
+        let orbit = &cfg.orbit;
+        if orbit.periapsis == 0.0 {
+            return (DVec3::ZERO, DVec3::ZERO);
+        }
+        // gravitational parameter µ of the two bodies, or from the period (µ = 4π²a³ / T²) around a massless parent
+        let parent_mu = cfg
+            .parent
+            .as_ref()
+            .and_then(|parent| self.bodies.get(parent))
+            .map_or(0.0, |parent| parent.mu);
+        let mu = if parent_mu > 0.0 {
+            parent_mu + cfg.mu
+        } else {
+            4.0 * PI * PI * orbit.semi_major.powi(3) / (orbit.period * orbit.period)
+        };
+        // time since the reference epoch, which is in MJD
+        let dt = (epoch - Epoch::from_mjd_utc(orbit.epoch)).to_seconds();
+        let (position, velocity) = if orbit.period != 0.0 {
+            let mean_anomaly = orbit.mean_anomaly.rad() + 2.0 * PI / orbit.period * dt;
+            kepler::perifocal_state(orbit.semi_major, orbit.eccentricity, mu, mean_anomaly)
+        } else {
+            let conic = Conic {
+                periapsis: orbit.periapsis,
+                eccentricity: orbit.eccentricity,
+            };
+            let mean_anomaly = orbit.mean_anomaly.rad() + conic.mean_motion(mu) * dt;
+            conic.perifocal_state(mu, mean_anomaly)
+        };
+        let rot = self.orbit_frame(cfg, epoch);
+        (rot * position, rot * velocity)
+    }
 
-        // Convert to millimeters and add parent offset
-        Some(parent_pos + pos_inertial.to_millimeters())
+    /// Solves for the position (m) and velocity (m/s), relative to a body, of a massless object on an orbit around it.
+    /// The elements are in the inertial frame, like those of the bodies, and the mean anomaly is at the orbit's epoch.
+    /// Returns None if the body is not found, or if the orbit has no size.
+    pub fn solve_orbit(&self, body: &str, orbit: &Orbit, epoch: Epoch) -> Option<(DVec3, DVec3)> {
+        let mu = self.bodies.get(body)?.mu;
+        let e = orbit.eccentricity;
+        let periapsis = if orbit.periapsis != 0.0 {
+            orbit.periapsis
+        } else {
+            orbit.semi_major * (1.0 - e)
+        };
+        if periapsis <= 0.0 || mu <= 0.0 {
+            return None;
+        }
+        let conic = Conic {
+            periapsis,
+            eccentricity: e,
+        };
+        let dt = (epoch - Epoch::from_mjd_utc(orbit.epoch)).to_seconds();
+        let mean_anomaly = orbit.mean_anomaly.rad() + conic.mean_motion(mu) * dt;
+        let (position, velocity) = conic.perifocal_state(mu, mean_anomaly);
+        let rot = DQuat::from_rotation_z(orbit.ascending_node.rad())
+            * DQuat::from_rotation_x(orbit.inclination.rad())
+            * DQuat::from_rotation_z(orbit.arg_of_pericenter.rad());
+        Some((rot * position, rot * velocity))
     }
 
-    /// Solves for the orbital velocity (m/s) of a body at a given time, in inertial frame.
-    /// Returns None if the body is not found or is fixed (zero semi-major axis).
-    #[allow(non_snake_case)]
-    pub fn solve_velocity(&self, body: &str, epoch: Epoch) -> Option<DVec3> {
+    /// Solves for the inertial velocity (m/s) of a point co-rotating with a body, given its offset (m) from the body's centre in the inertial frame.
+    /// This is the velocity of the body's surface, or of its atmosphere at rest.
+    pub fn solve_surface_velocity(&self, body: &str, offset: DVec3, epoch: Epoch) -> Option<DVec3> {
         let cfg = self.bodies.get(body)?;
-        // Static bodies have no orbital velocity
-        if cfg.orbit.semi_major == 0.0 {
-            return Some(DVec3::ZERO);
+        let mut velocity = self.solve_absolute_velocity(body, epoch)?;
+        if cfg.rotation.rotation_period != 0.0 {
+            let spin_rate = 2.0 * PI / cfg.rotation.rotation_period;
+            let spin_axis = self.solve_rotation(body, epoch)? * DVec3::Z;
+            velocity += spin_axis.cross(offset) * spin_rate;
         }
-        // Gravitational parameter µ from period: µ = 4π²a³ / T²
-        let a = cfg.orbit.semi_major;
-        let T = cfg.orbit.period;
-        let mu = 4.0 * PI * PI * a.powi(3) / (T * T);
-        // Time since reference epoch
-        let epoch0 = Epoch::from_mjd_utc(cfg.orbit.epoch);
-        let dt = (epoch - epoch0).to_seconds();
-        // Mean motion and anomaly
-        let n = 2.0 * PI / T;
-        let m = cfg.orbit.mean_anomaly + n * dt;
-        // Solve Kepler's equation for E
-        let e = cfg.orbit.eccentricity;
-        let mut E = m;
-        for _ in 0..50 {
-            let f = E - e * E.sin() - m;
-            let f_prime = 1.0 - e * E.cos();
-            E -= f / f_prime;
-        }
-        let cosE = E.cos();
-        let sinE = E.sin();
-        // True anomaly
-        let v = ((1.0 - e * e).sqrt() * sinE).atan2(cosE - e);
-        // Radius
-        let r = a * (1.0 - e * cosE);
-        // Specific angular momentum
-        let h = (mu * a * (1.0 - e * e)).sqrt();
-        // Radial and transverse velocity in orbital plane
-        let vr = mu / h * e * sinE;
-        let vtheta = mu / h * (1.0 + e * cosE);
-        let vx = vr * v.cos() - vtheta * v.sin();
-        let vy = vr * v.sin() + vtheta * v.cos();
-        let vel_orb = DVec3::new(vx, vy, 0.0);
-        // Rotate into inertial frame
-        let rot = DQuat::from_rotation_z(cfg.orbit.ascending_node)
-            * DQuat::from_rotation_x(cfg.orbit.inclination)
-            * DQuat::from_rotation_z(cfg.orbit.arg_of_pericenter);
-        Some(rot * vel_orb)
+        Some(velocity)
     }
 
     /// Solves for the rotation quaternion of a body at a given epoch.
+    pub fn solve_rotation(&self, body: &str, epoch: Epoch) -> Option<DQuat> {
+        if let Some(cached) = self.cached(body, epoch) {
+            return Some(cached.rotation);
+        }
+        Some(Self::rotation(self.bodies.get(body)?, epoch))
+    }
+
     /// Rotation parameters (eq_ascend_node, obliquity, rotation_epoch) are defined in the body's orbital frame,
     /// so we first orient the equator in inertial space via the orbit plane, then apply the body spin.
-    #[allow(non_snake_case)]
-    pub fn solve_rotation(&self, body: &str, epoch: Epoch) -> Option<DQuat> {
-        let cfg = self.bodies.get(body)?;
+    fn rotation(cfg: &Body, epoch: Epoch) -> DQuat {
         // no rotation period ⇒ identity orientation
         if cfg.rotation.rotation_period == 0.0 {
-            return Some(DQuat::IDENTITY);
+            return DQuat::IDENTITY;
         }
         // spin phase since reference rotation_epoch
         let epoch0 = Epoch::from_mjd_utc(cfg.rotation.rotation_epoch);
@@ -187,23 +492,32 @@ impl Orrery {
             / cfg.rotation.rotation_period;
 
         // 1) orbit frame → inertial: apply ascending_node, inclination, arg_of_pericenter
-        let orbit_rot = DQuat::from_rotation_z(cfg.orbit.ascending_node)
-            * DQuat::from_rotation_x(cfg.orbit.inclination)
-            * DQuat::from_rotation_z(cfg.orbit.arg_of_pericenter);
+        let orbit_rot = DQuat::from_rotation_z(cfg.orbit.ascending_node.rad())
+            * DQuat::from_rotation_x(cfg.orbit.inclination.rad())
+            * DQuat::from_rotation_z(cfg.orbit.arg_of_pericenter.rad());
 
         // 2) body equator within orbital plane: eq_ascend_node, obliquity, then spin
-        let eq_rot = DQuat::from_rotation_z(cfg.rotation.eq_ascend_node)
-            * DQuat::from_rotation_x(cfg.rotation.obliquity)
-            * DQuat::from_rotation_z(spin_angle - cfg.rotation.eq_ascend_node);
+        let eq_rot = DQuat::from_rotation_z(cfg.rotation.eq_ascend_node.rad())
+            * DQuat::from_rotation_x(cfg.rotation.obliquity.rad())
+            * DQuat::from_rotation_z(spin_angle - cfg.rotation.eq_ascend_node.rad());
 
         // full spin in inertial space
-        Some(orbit_rot * eq_rot)
+        orbit_rot * eq_rot
+    }
+
+    /// The cached state of a body, if the cache is for the epoch.
+    fn cached(&self, body: &str, epoch: Epoch) -> Option<&CachedBody> {
+        self.cache
+            .as_ref()
+            .filter(|cache| cache.epoch == epoch)?
+            .bodies
+            .get(body)
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::orrery::orrery_cfg::OrreryCfg;
+    use crate::orrery::orrery_cfg::{Angle, OrreryCfg};
     use anyhow::Result;
     use hifitime::Epoch;
 
@@ -213,8 +527,11 @@ mod tests {
 name: "sun-earth"
 bodies:
   - name: "Sun"
+    class: star
+    lumens: 3.828e26
     mass: "1 massSol"
   - name: "Earth"
+    class: planet
     parent: "Sun"
     mass: "1 massEarth"
     semi_major: "1 au"
@@ -227,7 +544,7 @@ bodies:
     epoch: 0.0
 "#;
         let cfg: OrreryCfg = serde_yml::from_str(yaml)?;
-        let ss = Orrery::init(cfg)?;
+        let ss = Orrery::init(cfg, Epoch::from_tai_seconds(0.0))?;
         for day in 0..365 {
             let epoch = Epoch::from_mjd_utc(day as f64);
             let pos = ss.solve_position("Earth", epoch).unwrap();
@@ -235,16 +552,466 @@ bodies:
         }
         Ok(())
     }
+    #[test]
+    fn nodal_regression_around_oblate_parent() -> Result<()> {
+        let yaml = r#"
+name: "oblate"
+bodies:
+  - name: "Planet"
+    class: planet
+    mass: "12 massEarth"
+    radius: 1.0e7
+    j2: 1.0e-3
+    rotation_period: "24 h"
+  - name: "Moon"
+    class: planet
+    parent: "Planet"
+    mass: 1.0e20
+    semi_major: 3.0e7
+    inclination: "0.3 rad"
+"#;
+        let cfg: OrreryCfg = serde_yml::from_str(yaml)?;
+        let ss = Orrery::init(cfg, Epoch::from_tai_seconds(0.0))?;
+        let moon = ss.get_body("Moon").unwrap();
+        let n = 2.0 * PI / moon.orbit.period;
+        let expected_rate = -1.5 * n * 1.0e-3 * (1.0e7f64 / 3.0e7).powi(2) * 0.3f64.cos();
+
+        let epoch = Epoch::from_mjd_utc(30.0);
+        let position = ss.solve_position("Moon", epoch).unwrap().as_dvec3();
+        let velocity = ss.solve_velocity("Moon", epoch).unwrap();
+        let normal = position.cross(velocity).normalize();
+        // the node regresses about the spin axis, keeping the inclination
+        assert!((normal.z - 0.3f64.cos()).abs() < 1e-9);
+        let node = normal.x.atan2(-normal.y);
+        let expected = expected_rate * (epoch - Epoch::from_mjd_utc(0.0)).to_seconds();
+        assert!(expected < -0.1);
+        assert!((node - expected).abs() < 1e-6, "{node} vs {expected}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_particle_co_moves_with_body() -> Result<()> {
+        let yaml = r#"
+name: "co-moving"
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mu: 1.32712440018e20
+  - name: "Rock"
+    class: planet
+    parent: "Star"
+    mass: 1.0e10
+    semi_major: "1 au"
+    period: "200 d"
+    eccentricity: 0.2
+    inclination: 0.1
+    mean_anomaly: 1.0
+"#;
+        let cfg: OrreryCfg = serde_yml::from_str(yaml)?;
+        let ss = Orrery::init(cfg, Epoch::from_tai_seconds(0.0))?;
+        // the configured period yields to the one implied by the star's gravitational parameter
+        assert!((ss.get_body("Rock").unwrap().orbit.period / 86_400.0 - 365.25).abs() < 0.1);
+
+        // a vessel on the body's orbit, pulled by the star the way vessels are
+        let start = Epoch::from_mjd_utc(0.0);
+        let mut position = ss.solve_position("Rock", start).unwrap().as_dvec3() / 1000.0;
+        let mut velocity = ss.solve_velocity("Rock", start).unwrap();
+        let dt = 60.0;
+        let accel = |position: DVec3, t: f64| {
+            ss.solve_gravity(
+                "Star",
+                position,
+                start + hifitime::Duration::from_seconds(t),
+            )
+            .unwrap()
+        };
+        let steps = 20 * 24 * 60;
+        for step in 0..steps {
+            let t = step as f64 * dt;
+            velocity += accel(position, t) * dt / 2.0;
+            position += velocity * dt;
+            velocity += accel(position, t + dt) * dt / 2.0;
+        }
+        let end = start + hifitime::Duration::from_seconds(steps as f64 * dt);
+        let body = ss.solve_position("Rock", end).unwrap().as_dvec3() / 1000.0;
+        let drift = (position - body).length();
+        assert!(drift < 1_000.0, "drifted {drift} m from the body");
+        Ok(())
+    }
+
+    #[test]
+    fn angle_units() -> Result<()> {
+        let body = |cfg: &str| -> Result<Body> {
+            let ss = Orrery::init(serde_yml::from_str(cfg)?, Epoch::from_tai_seconds(0.0))?;
+            Ok(ss.get_body("A").unwrap().clone())
+        };
+        let degrees = body(
+            r#"
+name: "degrees"
+bodies:
+  - name: "A"
+    class: planet
+    inclination: 90
+    obliquity: "0.5 rad"
+    ascending_node: "-45 deg"
+"#,
+        )?;
+        assert!((degrees.orbit.inclination.rad() - PI / 2.0).abs() < 1e-12);
+        assert!((degrees.rotation.obliquity.rad() - 0.5).abs() < 1e-12);
+        assert!((degrees.orbit.ascending_node.rad() + PI / 4.0).abs() < 1e-12);
+
+        let radians = body(
+            r#"
+name: "radians"
+angle_unit: rad
+bodies:
+  - name: "A"
+    class: planet
+    inclination: 0.25
+    mean_anomaly: "180 deg"
+"#,
+        )?;
+        assert!((radians.orbit.inclination.rad() - 0.25).abs() < 1e-12);
+        assert!((radians.orbit.mean_anomaly.rad() - PI).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "never given a unit")]
+    fn unresolved_angles_are_caught() {
+        Angle::Bare(90.0).rad();
+    }
+
+    #[test]
+    fn n_body_follows_two_body_conic() -> Result<()> {
+        let yaml = |propagation: &str| {
+            format!(
+                r#"
+name: "two-body"
+{propagation}
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mass: "1 massSol"
+  - name: "Rock"
+    class: planet
+    parent: "Star"
+    mass: 1.0e10
+    semi_major: "0.4 au"
+    eccentricity: 0.2
+    inclination: 10
+"#
+            )
+        };
+        let kepler = Orrery::init(
+            serde_yml::from_str(&yaml(""))?,
+            Epoch::from_tai_seconds(0.0),
+        )?;
+        let mut n_body = Orrery::init(
+            serde_yml::from_str(&yaml("propagation:\n  mode: n_body\n  step: \"10 min\""))?,
+            Epoch::from_tai_seconds(0.0),
+        )?;
+
+        // between two samples of the ephemeris
+        let epoch = Epoch::from_tai_seconds(30.3 * 86_400.0 + 17.0);
+        n_body.advance(epoch);
+        let drift = (n_body.solve_position("Rock", epoch).unwrap()
+            - kepler.solve_position("Rock", epoch).unwrap())
+        .to_meters_64()
+        .length();
+        assert!(drift < 100.0, "drifted {drift} m from the conic");
+        let velocity_error = (n_body.solve_velocity("Rock", epoch).unwrap()
+            - kepler.solve_velocity("Rock", epoch).unwrap())
+        .length();
+        assert!(
+            velocity_error < 1e-3,
+            "velocity off by {velocity_error} m/s"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hyperbolic_flyby_from_periapsis() -> Result<()> {
+        let yaml = r#"
+name: "flyby"
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mass: "1 massSol"
+  - name: "Rogue"
+    class: planet
+    parent: "Star"
+    mass: 1.0e12
+    periapsis: "0.5 au"
+    eccentricity: 1.8
+    inclination: 30
+"#;
+        let ss = Orrery::init(serde_yml::from_str(yaml)?, Epoch::from_tai_seconds(0.0))?;
+        let rogue = ss.get_body("Rogue").unwrap();
+        assert!(rogue.orbit.semi_major < 0.0);
+        let mu = ss.get_body("Star").unwrap().mu + rogue.mu;
+
+        let start = Epoch::from_mjd_utc(0.0);
+        let periapsis = ss.solve_position("Rogue", start).unwrap().to_meters_64();
+        assert!((periapsis.length() / rogue.orbit.periapsis - 1.0).abs() < 1e-9);
+
+        let energy = |epoch| {
+            let position = ss.solve_position("Rogue", epoch).unwrap().to_meters_64();
+            let velocity = ss.solve_velocity("Rogue", epoch).unwrap();
+            velocity.length_squared() / 2.0 - mu / position.length()
+        };
+        for days in [-400.0, 30.0, 1000.0] {
+            let epoch = start + Duration::from_seconds(days * 86_400.0);
+            assert!((energy(epoch) / energy(start) - 1.0).abs() < 1e-6);
+        }
+        // the hyperbolic excess speed √(−μ/a) is the same as the one implied by the energy
+        assert!((2.0 * energy(start) / (-mu / rogue.orbit.semi_major) - 1.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn massless_orbits() -> Result<()> {
+        let yaml = r#"
+name: "planet"
+bodies:
+  - name: "Planet"
+    class: planet
+    mass: "1 massEarth"
+"#;
+        let ss = Orrery::init(serde_yml::from_str(yaml)?, Epoch::from_tai_seconds(0.0))?;
+        let mu = ss.get_body("Planet").unwrap().mu;
+        let epoch = Epoch::from_mjd_utc(10.0);
+
+        // circular, tilted about the X axis
+        let circular = Orbit {
+            semi_major: 7.0e6,
+            inclination: Angle::Radians(0.5),
+            epoch: 10.0,
+            ..Default::default()
+        };
+        let (position, velocity) = ss.solve_orbit("Planet", &circular, epoch).unwrap();
+        assert!((position.length() / 7.0e6 - 1.0).abs() < 1e-12);
+        assert!((velocity.length() / (mu / 7.0e6).sqrt() - 1.0).abs() < 1e-12);
+        let normal = position.cross(velocity).normalize();
+        assert!((normal.z - 0.5f64.cos()).abs() < 1e-12);
+
+        // elliptic, back at periapsis after a period
+        let elliptic = Orbit {
+            periapsis: 7.0e6,
+            eccentricity: 0.3,
+            epoch: 10.0,
+            ..Default::default()
+        };
+        let period = 2.0 * PI * ((7.0e6f64 / 0.7).powi(3) / mu).sqrt();
+        let later = epoch + Duration::from_seconds(period);
+        let (position, velocity) = ss.solve_orbit("Planet", &elliptic, later).unwrap();
+        assert!((position - DVec3::X * 7.0e6).length() < 1e-3);
+        assert!((velocity.length() / (mu * 1.3 / 7.0e6).sqrt() - 1.0).abs() < 1e-9);
+
+        // an orbit needs a size and a body
+        assert!(ss.solve_orbit("Planet", &Orbit::default(), epoch).is_none());
+        assert!(ss.solve_orbit("Moon", &circular, epoch).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn barycentric_system_has_no_net_momentum() -> Result<()> {
+        let yaml = |barycentric: bool| {
+            format!(
+                r#"
+name: "wobbly"
+barycentric: {barycentric}
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mass: "1 massSol"
+  - name: "Giant"
+    class: planet
+    parent: "Star"
+    mass: 1.9e27
+    semi_major: "5.2 au"
+    eccentricity: 0.05
+  - name: "Moon"
+    class: planet
+    parent: "Giant"
+    mass: 1.5e23
+    semi_major: 1.07e9
+    eccentricity: 0.01
+    inclination: 2
+"#
+            )
+        };
+        let fixed = Orrery::init(
+            serde_yml::from_str(&yaml(false))?,
+            Epoch::from_tai_seconds(0.0),
+        )?;
+        let mut wobbly = Orrery::init(
+            serde_yml::from_str(&yaml(true))?,
+            Epoch::from_tai_seconds(0.0),
+        )?;
+        let epoch = Epoch::from_tai_seconds(123.0 * 86_400.0);
+        for cached in [false, true] {
+            if cached {
+                wobbly.advance(epoch);
+            }
+            let (mut moment, mut momentum, mut total) = (DVec3::ZERO, DVec3::ZERO, 0.0);
+            for body in wobbly.iter() {
+                let position = wobbly.solve_position(&body.name, epoch).unwrap();
+                let velocity = wobbly.solve_absolute_velocity(&body.name, epoch).unwrap();
+                moment += position.to_meters_64() * body.mu;
+                momentum += velocity * body.mu;
+                total += body.mu;
+            }
+            assert!(
+                (moment / total).length() < 1.0,
+                "barycentre off by {moment}"
+            );
+            assert!(
+                (momentum / total).length() < 1e-9,
+                "net momentum {momentum}"
+            );
+
+            // the star is displaced by about a solar radius, and the conics are unchanged, though the giant's is now
+            // that of its barycentre with the moon
+            let star = wobbly.solve_position("Star", epoch).unwrap().to_meters_64();
+            assert!(star.length() > 5e8);
+            let separation = |ss: &Orrery, body: &str, parent: &str| {
+                (ss.solve_position(body, epoch).unwrap()
+                    - ss.solve_position(parent, epoch).unwrap())
+                .to_meters_64()
+            };
+            let moon = separation(&wobbly, "Moon", "Giant");
+            assert!((moon - separation(&fixed, "Moon", "Giant")).length() < 1.0);
+            let (giant_mu, moon_mu) = (
+                wobbly.get_body("Giant").unwrap().mu,
+                wobbly.get_body("Moon").unwrap().mu,
+            );
+            let barycentre =
+                separation(&wobbly, "Giant", "Star") + moon * moon_mu / (giant_mu + moon_mu);
+            let drift = (barycentre - separation(&fixed, "Giant", "Star")).length();
+            assert!(drift < 1.0, "giant's barycentre moved {drift} m");
+        }
+        Ok(())
+    }
+
+    /// A star system from the assets, without the atmospheres whose tables live in separate files.
+    fn asset_system(path: &str) -> Result<Orrery> {
+        let mut cfg: OrreryCfg = toml::from_str(&std::fs::read_to_string(path)?)?;
+        for body in &mut cfg.bodies {
+            body.atmosphere = None;
+        }
+        Orrery::init(cfg, Epoch::from_tai_seconds(0.0))
+    }
+
+    fn taale() -> Result<Orrery> {
+        asset_system("assets/stars/taale.star.toml")
+    }
+
+    #[test]
+    fn every_asset_system_loads() -> Result<()> {
+        for entry in std::fs::read_dir("assets/stars")? {
+            let path = entry?.path();
+            let path = path.to_str().unwrap();
+            if path.ends_with(".star.toml") {
+                let ss = asset_system(path).with_context(|| format!("loading {path}"))?;
+                assert!(ss.iter().next().is_some(), "{path} has no bodies");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cached_queries_match() -> Result<()> {
+        let mut cached = taale()?;
+        let uncached = taale()?;
+        let epoch = Epoch::from_tai_seconds(12_345.6);
+        cached.advance(epoch);
+        for body in uncached.iter() {
+            let name = &body.name;
+            assert_eq!(
+                cached.solve_position(name, epoch),
+                uncached.solve_position(name, epoch)
+            );
+            assert!(
+                (cached.solve_velocity(name, epoch).unwrap()
+                    - uncached.solve_velocity(name, epoch).unwrap())
+                .length()
+                    < 1e-9
+            );
+            assert!(
+                cached
+                    .solve_rotation(name, epoch)
+                    .unwrap()
+                    .abs_diff_eq(uncached.solve_rotation(name, epoch).unwrap(), 1e-12)
+            );
+        }
+        Ok(())
+    }
+
+    /// Times the orrery queries of a tick with 1000 vessels around Pannea, with and without the per-tick cache. Run
+    /// with `cargo test ephemeris_cache_speedup -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn ephemeris_cache_speedup() -> Result<()> {
+        let mut orrery = taale()?;
+        let ticks = 100;
+        let run = |orrery: &Orrery, epoch: Epoch| {
+            // what move_orrery, update_aero_env and the gravity of 1000 vessels ask for
+            let names = orrery
+                .iter()
+                .map(|body| body.name.clone())
+                .collect::<Vec<_>>();
+            for name in &names {
+                std::hint::black_box(orrery.solve_position(name, epoch));
+                std::hint::black_box(orrery.solve_rotation(name, epoch));
+            }
+            for _ in 0..1000 {
+                let offset = DVec3::new(1.7e7, 0.0, 0.0);
+                std::hint::black_box(orrery.solve_velocity("Pannea", epoch));
+                std::hint::black_box(orrery.solve_surface_velocity("Pannea", offset, epoch));
+                for name in &names {
+                    std::hint::black_box(orrery.solve_gravity(name, offset, epoch));
+                }
+            }
+        };
+
+        let start = std::time::Instant::now();
+        for tick in 0..ticks {
+            run(&orrery, Epoch::from_tai_seconds(tick as f64 / 64.0));
+        }
+        let uncached = start.elapsed();
+
+        let start = std::time::Instant::now();
+        for tick in 0..ticks {
+            let epoch = Epoch::from_tai_seconds(tick as f64 / 64.0);
+            orrery.advance(epoch);
+            run(&orrery, epoch);
+        }
+        let cached = start.elapsed();
+
+        println!(
+            "{ticks} ticks: {uncached:?} uncached, {cached:?} cached ({:.1}x)",
+            uncached.as_secs_f64() / cached.as_secs_f64()
+        );
+        Ok(())
+    }
+
     #[test]
     fn default_solve_rotation_identity() -> Result<()> {
         let yaml = r#"
 name: "test"
 bodies:
   - name: "A"
+    class: planet
     mass: "1 massEarth"
 "#;
         let cfg: OrreryCfg = serde_yml::from_str(yaml)?;
-        let ss = Orrery::init(cfg)?;
+        let ss = Orrery::init(cfg, Epoch::from_tai_seconds(0.0))?;
         let epoch = Epoch::from_mjd_utc(42.0);
         assert_eq!(ss.solve_rotation("A", epoch).unwrap(), DQuat::IDENTITY);
         Ok(())
@@ -1,18 +1,27 @@
 pub mod aerodynamics;
+pub mod collision;
 pub mod docking;
+pub mod geodesy;
+pub mod orbit;
+pub mod spatial;
 
 use bevy::{
+    ecs::system::SystemParam,
     math::{DMat3, DQuat, DVec3},
     prelude::*,
 };
-use hifitime::Epoch;
+use hifitime::{Duration, Epoch};
 
 use crate::{
     GameState,
     orrery::{Celestial, Orrery},
     physics::{
         aerodynamics::{AeroEnv, run_aero},
+        collision::run_collision,
         docking::{DockChild, run_docking},
+        geodesy::{SurfaceState, run_geodesy},
+        orbit::{PredictedOrbit, run_orbit},
+        spatial::run_spatial,
     },
     precision::{PreciseTransform, ToMetersExt, ToMillimetersExt},
 };
@@ -21,8 +30,16 @@ pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<SimClock>();
         app.add_systems(Update, gizmos);
-        app.add_plugins((run_aero, run_docking));
+        app.add_plugins((
+            run_aero,
+            run_collision,
+            run_docking,
+            run_geodesy,
+            run_orbit,
+            run_spatial,
+        ));
         app.add_systems(
             FixedUpdate,
             (gravity, apply_forces).run_if(in_state(GameState::Game)),
@@ -84,7 +101,9 @@ fn apply_forces(
     AccumulatedForce,
     AccumulatedTorque,
     PreviousAcceleration,
-    AeroEnv
+    AeroEnv,
+    PredictedOrbit,
+    SurfaceState
 )]
 pub struct RigidBody;
 
@@ -120,6 +139,44 @@ impl Default for MassProps {
     }
 }
 
+impl MassProps {
+    /// The mass properties of a body made of solid boxes, each given by its mass (kg), centre (m), rotation and size
+    /// (m) in the body's frame, and the centre of mass (m) of the boxes in that frame. The inertia is about that
+    /// centre of mass.
+    pub fn from_boxes(
+        boxes: impl IntoIterator<Item = (f64, DVec3, DQuat, DVec3)>,
+    ) -> (Self, DVec3) {
+        let boxes = boxes.into_iter().collect::<Vec<_>>();
+        let mass = boxes.iter().map(|&(m, ..)| m).sum::<f64>();
+        let center_of_mass = if mass > 0.0 {
+            boxes.iter().map(|&(m, c, ..)| m * c).sum::<DVec3>() / mass
+        } else {
+            DVec3::ZERO
+        };
+
+        let mut inertia = DMat3::ZERO;
+        for (m, center, rotation, size) in boxes {
+            let s2 = size * size;
+            let local =
+                DMat3::from_diagonal(DVec3::new(s2.y + s2.z, s2.x + s2.z, s2.x + s2.y) * m / 12.0);
+            let rot = DMat3::from_quat(rotation);
+            // parallel axis theorem: I + m (‖r‖²E − r rᵀ)
+            let r = center - center_of_mass;
+            let outer = DMat3::from_cols(r * r.x, r * r.y, r * r.z);
+            inertia +=
+                rot * local * rot.transpose() + m * (r.length_squared() * DMat3::IDENTITY - outer);
+        }
+        (
+            Self {
+                mass,
+                inertia,
+                inertia_inv: inertia.inverse(),
+            },
+            center_of_mass,
+        )
+    }
+}
+
 #[derive(Component)]
 #[relationship(relationship_target = HasWithinSoi)]
 pub struct WithinSoi(pub Entity);
@@ -132,6 +189,7 @@ pub struct HasWithinSoi(Vec<Entity>);
 fn gravity(
     commands: ParallelCommands,
     star: Res<Orrery>,
+    time: SimTime,
     celestials: Query<(Entity, &Celestial, &PreciseTransform)>,
     mut objects: Query<(
         Entity,
@@ -144,19 +202,18 @@ fn gravity(
     objects
         .par_iter_mut()
         .for_each(|(object_ent, props, obj_ptf, mut force, soi)| {
-            const GEE: f64 = 6.6473e-11;
+            let epoch = time.epoch();
             let mut closest_celestial = None;
             let mut biggest_gravity = 0.0;
             for (cel_entity, celestial, cel_ptf) in celestials.iter() {
-                let cel_mass = star.get_body(&celestial.0).unwrap().mass;
-                let obj_to_cel = (cel_ptf.translation_mm - obj_ptf.translation_mm).to_meters_64();
-                let r_squared = obj_to_cel.length_squared();
-                let f = GEE * cel_mass * props.mass / r_squared;
-                if f > biggest_gravity {
-                    biggest_gravity = f;
+                let offset = (obj_ptf.translation_mm - cel_ptf.translation_mm).to_meters_64();
+                let acc = star.solve_gravity(&celestial.0, offset, epoch).unwrap();
+                let g = acc.length();
+                if g > biggest_gravity {
+                    biggest_gravity = g;
                     closest_celestial = Some(cel_entity);
                 }
-                force.0 += obj_to_cel.normalize() * f;
+                force.0 += acc * props.mass;
             }
             if let Some(cel_entity) = closest_celestial {
                 if soi.map(|s| s.0) != Some(cel_entity) {
@@ -168,8 +225,42 @@ fn gravity(
         });
 }
 
-pub fn sim_time(t: &Time) -> Epoch {
-    Epoch::from_tai_seconds(t.elapsed_secs_f64())
+/// Maps the time elapsed in the app onto the simulated calendar.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct SimClock {
+    /// Epoch simulated when the game started.
+    pub start: Epoch,
+    /// Time (s) elapsed in the app when the game started.
+    pub started_at: f64,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self {
+            start: Epoch::from_tai_seconds(0.0),
+            started_at: 0.0,
+        }
+    }
+}
+
+impl SimClock {
+    /// The simulated epoch once `elapsed` seconds have passed in the app.
+    pub fn epoch(&self, elapsed: f64) -> Epoch {
+        self.start + Duration::from_seconds(elapsed - self.started_at)
+    }
+}
+
+/// The simulated epoch of the current tick, or of the current frame outside of the fixed schedules.
+#[derive(SystemParam)]
+pub struct SimTime<'w> {
+    time: Res<'w, Time>,
+    clock: Res<'w, SimClock>,
+}
+
+impl SimTime<'_> {
+    pub fn epoch(&self) -> Epoch {
+        self.clock.epoch(self.time.elapsed_secs_f64())
+    }
 }
 
 fn gizmos(mut gizmos: Gizmos, objects: Query<&Transform, With<MassProps>>) {
@@ -2,13 +2,17 @@ mod aero_env;
 pub use aero_env::*;
 mod aero_model;
 pub use aero_model::*;
+mod atmosphere;
+pub use atmosphere::*;
+mod wind;
+pub use wind::*;
 
 use std::f64::consts::PI;
 
 use bevy::prelude::*;
 
 use crate::GameState;
-use crate::physics::aerodynamics::aero_model::calc_aerodynamics;
+pub(crate) use crate::physics::aerodynamics::aero_model::calc_aerodynamics;
 
 use crate::physics::{AccumulatedForce, AccumulatedTorque, AngularVelocity};
 
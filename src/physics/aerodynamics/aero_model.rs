@@ -1,11 +1,14 @@
 use std::f64::consts::PI;
 
-use bevy::{math::DVec3, prelude::*};
+use bevy::{
+    math::{DQuat, DVec3},
+    prelude::*,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     physics::{AccumulatedForce, AccumulatedTorque, AngularVelocity, aerodynamics::AeroEnv},
-    precision::{PreciseTransform, ToMetersExt},
+    precision::{PreciseTransform, ToMetersExt, ToMillimetersExt},
 };
 
 pub(crate) fn calc_aerodynamics(
@@ -52,33 +55,27 @@ impl AeroModel {
         relative_angvel: DVec3,
         env: &AeroEnv,
     ) -> AeroModelOutput {
-        let make_flow = |speed: f64| -> Flow {
-            let mach = (speed / env.speed_of_sound).abs();
-            let q = 0.5 * env.density * speed * speed;
-            Flow { mach, q }
-        };
-
         let mut total_force = DVec3::ZERO;
         let mut total_torque = DVec3::ZERO;
 
         let v_body = relative_airspeed;
-        let speed_body = v_body.length();
-        if speed_body > 0.0 {
-            let flow_body = make_flow(speed_body);
-            let drag_mag = self.main.drag(flow_body);
-            total_force += -v_body / speed_body * drag_mag;
-        }
+        let main = self.main.eval(v_body, relative_angvel, env);
+        total_force += main.force;
+        total_torque += main.torque;
 
         for (wing_tf, wing) in &self.wings {
             let r = wing_tf.translation_mm.to_meters_64();
-            let v_local_body = v_body - relative_angvel.cross(r);
+            let v_local_body = v_body + relative_angvel.cross(r);
 
             let v_local_wing = wing_tf.rotation.inverse() * v_local_body;
             let speed_wing = v_local_wing.length();
+            if speed_wing == 0.0 {
+                continue;
+            }
 
-            let flow = make_flow(speed_wing);
+            let flow = Flow::new(speed_wing, env);
             let aoa = v_local_wing.y.atan2(-v_local_wing.z);
-            let WingForces { lift, drag } = wing.eval_forces(aoa, flow);
+            let WingForces { lift, drag, moment } = wing.eval_forces(aoa, flow);
             let v_dir = v_local_wing / speed_wing;
             let drag_dir_local = -v_dir;
             let span_axis_local = DVec3::X;
@@ -89,7 +86,8 @@ impl AeroModel {
             let f_body = wing_tf.rotation * f_local;
 
             total_force += f_body;
-            total_torque += r.cross(f_body);
+            // the pitching moment acts about the span axis, positive nose-up
+            total_torque += r.cross(f_body) + wing_tf.rotation * (span_axis_local * moment);
         }
 
         AeroModelOutput {
@@ -105,32 +103,225 @@ pub struct AeroModelOutput {
     pub force: DVec3,
 }
 
+#[derive(Clone, Debug)]
 pub enum MainBodyModel {
     Sphere(f64),
+    /// A cylinder along the local Z axis.
+    Cylinder {
+        radius: f64,
+        length: f64,
+    },
+    /// A box with the given size (m). Faces are listed as +X, -X, +Y, -Y, +Z, -Z, with the area (m²) exposed to the airflow;
+    /// faces covered by neighbouring parts expose less than their full area.
+    Box {
+        size: DVec3,
+        face_areas: [f64; 6],
+    },
+    /// A pointed nose cone along the local Z axis, with its tip towards -Z.
+    Nosecone {
+        radius: f64,
+        length: f64,
+    },
+    /// Several shapes, each placed relative to the vessel's centre of mass.
+    Composite(Vec<(PreciseTransform, MainBodyModel)>),
 }
 
 impl MainBodyModel {
-    pub fn drag(&self, flow: Flow) -> f64 {
+    /// A box with all its faces exposed.
+    pub fn cuboid(size: DVec3) -> Self {
+        let (yz, xz, xy) = (size.y * size.z, size.x * size.z, size.x * size.y);
+        MainBodyModel::Box {
+            size,
+            face_areas: [yz, yz, xz, xz, xy, xy],
+        }
+    }
+
+    /// Builds composite elements out of axis-aligned boxes, given as (centre, size) in meters.
+    /// Faces of boxes that touch each other are shielded from the airflow.
+    pub fn shielded_boxes(boxes: &[(DVec3, DVec3)]) -> Vec<(PreciseTransform, MainBodyModel)> {
+        let mut areas = boxes
+            .iter()
+            .map(|&(_, size)| match MainBodyModel::cuboid(size) {
+                MainBodyModel::Box { face_areas, .. } => face_areas,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        for (i, &(center_a, size_a)) in boxes.iter().enumerate() {
+            for (j, &(center_b, size_b)) in boxes.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                for axis in 0..3 {
+                    // does the positive face of A touch the negative face of B?
+                    let face_a = center_a[axis] + size_a[axis] / 2.0;
+                    let face_b = center_b[axis] - size_b[axis] / 2.0;
+                    if (face_a - face_b).abs() > 1e-3 {
+                        continue;
+                    }
+                    let overlap = |k: usize| {
+                        let lo = (center_a[k] - size_a[k] / 2.0).max(center_b[k] - size_b[k] / 2.0);
+                        let hi = (center_a[k] + size_a[k] / 2.0).min(center_b[k] + size_b[k] / 2.0);
+                        (hi - lo).max(0.0)
+                    };
+                    let shared = overlap((axis + 1) % 3) * overlap((axis + 2) % 3);
+                    areas[i][2 * axis] = (areas[i][2 * axis] - shared).max(0.0);
+                    areas[j][2 * axis + 1] = (areas[j][2 * axis + 1] - shared).max(0.0);
+                }
+            }
+        }
+
+        boxes
+            .iter()
+            .zip(areas)
+            .map(|(&(center, size), face_areas)| {
+                (
+                    PreciseTransform {
+                        translation_mm: center.to_millimeters(),
+                        rotation: DQuat::IDENTITY,
+                    },
+                    MainBodyModel::Box { size, face_areas },
+                )
+            })
+            .collect()
+    }
+
+    /// Computes the drag force and torque, given the airspeed and angular velocity in this shape's frame.
+    pub fn eval(&self, airspeed: DVec3, angvel: DVec3, env: &AeroEnv) -> AeroModelOutput {
+        if let MainBodyModel::Composite(elements) = self {
+            let mut force = DVec3::ZERO;
+            let mut torque = DVec3::ZERO;
+            for (tf, element) in elements {
+                let r = tf.translation_mm.to_meters_64();
+                let rot_inv = tf.rotation.inverse();
+                let out = element.eval(
+                    rot_inv * (airspeed + angvel.cross(r)),
+                    rot_inv * angvel,
+                    env,
+                );
+                let f = tf.rotation * out.force;
+                force += f;
+                torque += tf.rotation * out.torque + r.cross(f);
+            }
+            return AeroModelOutput { torque, force };
+        }
+
+        let speed = airspeed.length();
+        if speed == 0.0 {
+            return AeroModelOutput {
+                torque: DVec3::ZERO,
+                force: DVec3::ZERO,
+            };
+        }
+        let dir = airspeed / speed;
+        let flow = Flow::new(speed, env);
+        AeroModelOutput {
+            torque: DVec3::ZERO,
+            force: -dir * self.drag_area(dir, flow) * flow.q,
+        }
+    }
+
+    /// The drag area (C_d·A, m²) for a body moving along `dir` (a unit vector in this shape's frame).
+    fn drag_area(&self, dir: DVec3, flow: Flow) -> f64 {
         match self {
             MainBodyModel::Sphere(radius) => {
-                // Cross-sectional area of sphere
-                let area = PI * radius * radius;
-
-                // Simple drag coefficient for a sphere
-                // Could be made more sophisticated with Reynolds number dependence
-                let cd = if flow.mach < 0.8 {
-                    0.47 // Typical subsonic value for a sphere
+                let re = flow.reynolds_per_m * 2.0 * radius;
+                sphere_cd(re) * bluff_mach_factor(flow.mach) * PI * radius * radius
+            }
+            MainBodyModel::Cylinder { radius, length } => {
+                // crossflow principle: axial and normal components are treated independently
+                let cos = dir.z.abs();
+                let sin = (1.0 - cos * cos).max(0.0).sqrt();
+                let cf = skin_friction(flow.reynolds_per_m * length);
+                let cd_axial = 0.82 * bluff_mach_factor(flow.mach) + cf * 2.0 * length / radius;
+                let cd_cross = cylinder_cross_cd(flow.reynolds_per_m * 2.0 * radius)
+                    * bluff_mach_factor(flow.mach);
+                cd_axial * PI * radius * radius * cos.powi(3)
+                    + cd_cross * 2.0 * radius * length * sin.powi(3)
+            }
+            MainBodyModel::Box { size, face_areas } => {
+                // faces facing into the airflow take pressure drag, faces along it take skin friction
+                let normals = [dir.x, -dir.x, dir.y, -dir.y, dir.z, -dir.z];
+                let exposed = normals
+                    .iter()
+                    .zip(face_areas)
+                    .map(|(d, area)| d.max(0.0) * area)
+                    .sum::<f64>();
+                let wetted = normals
+                    .iter()
+                    .zip(face_areas)
+                    .map(|(d, area)| (1.0 - d * d) * area)
+                    .sum::<f64>();
+                let cf = skin_friction(flow.reynolds_per_m * size.dot(dir.abs()));
+                1.05 * bluff_mach_factor(flow.mach) * exposed + cf * wetted
+            }
+            MainBodyModel::Nosecone { radius, length } => {
+                let cos = dir.z.abs();
+                let sin = (1.0 - cos * cos).max(0.0).sqrt();
+                let frontal = PI * radius * radius;
+                let cd_axial = if dir.z < 0.0 {
+                    // tip first: skin friction, base drag, and supersonic wave drag
+                    let half_angle = (radius / length).atan();
+                    let wetted = PI * radius * (radius * radius + length * length).sqrt();
+                    let wave = 2.0 * half_angle.sin().powi(2) * smoothstep((flow.mach - 0.8) / 0.4);
+                    skin_friction(flow.reynolds_per_m * length) * wetted / frontal + 0.12 + wave
                 } else {
-                    // Simple supersonic increase
-                    0.47 * (1.0 + 0.5 * (flow.mach - 0.8))
+                    // base first: a blunt face
+                    0.82 * bluff_mach_factor(flow.mach)
                 };
-
-                cd * flow.q * area
+                let cd_cross =
+                    cylinder_cross_cd(flow.reynolds_per_m * radius) * bluff_mach_factor(flow.mach);
+                cd_axial * frontal * cos.powi(3) + cd_cross * radius * length * sin.powi(3)
             }
+            MainBodyModel::Composite(elements) => elements
+                .iter()
+                .map(|(tf, element)| element.drag_area(tf.rotation.inverse() * dir, flow))
+                .sum(),
         }
     }
 }
 
+#[inline]
+fn smoothstep(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Flat-plate skin-friction coefficient: laminar below a Reynolds number of 5·10⁵, turbulent above.
+fn skin_friction(re: f64) -> f64 {
+    let re = re.max(1.0);
+    if re < 5e5 {
+        1.328 / re.sqrt()
+    } else {
+        0.455 / re.log10().powf(2.58)
+    }
+}
+
+/// Sphere drag coefficient (Clift–Gauvin correlation), with the drag crisis around Re ≈ 3·10⁵.
+fn sphere_cd(re: f64) -> f64 {
+    let re = re.max(1e-3);
+    let cd = 24.0 / re * (1.0 + 0.15 * re.powf(0.687)) + 0.42 / (1.0 + 42_500.0 * re.powf(-1.16));
+    let crisis = smoothstep((re.log10() - 5.3) / 0.4);
+    cd * (1.0 - crisis) + 0.2 * crisis
+}
+
+/// Drag coefficient of a cylinder in crossflow, based on the diameter Reynolds number.
+fn cylinder_cross_cd(re: f64) -> f64 {
+    let log_re = re.max(1.0).log10();
+    if log_re < 5.3 {
+        1.2
+    } else if log_re < 5.7 {
+        1.2 - 0.9 * smoothstep((log_re - 5.3) / 0.4)
+    } else {
+        0.3 + 0.4 * smoothstep((log_re - 5.7) / 1.0)
+    }
+}
+
+/// Growth of bluff-body pressure drag through the transonic regime, relative to incompressible flow.
+fn bluff_mach_factor(mach: f64) -> f64 {
+    1.0 + 0.9 * smoothstep((mach - 0.6) / 0.9)
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Wing {
     pub area: f64,
@@ -168,22 +359,43 @@ impl Default for WingDetails {
 pub struct Flow {
     pub mach: f64,
     pub q: f64,
+    /// Reynolds number per meter of characteristic length.
+    pub reynolds_per_m: f64,
+}
+
+impl Flow {
+    pub fn new(speed: f64, env: &AeroEnv) -> Self {
+        Self {
+            mach: (speed / env.speed_of_sound).abs(),
+            q: 0.5 * env.density * speed * speed,
+            reynolds_per_m: env.density * speed.abs() / env.viscosity.max(1e-12),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct WingCoeffs {
     pub cl: f64,
     pub cd: f64,
+    pub cm: f64,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct WingForces {
     pub lift: f64,
     pub drag: f64,
+    /// Pitching moment (N·m) about the span axis.
+    pub moment: f64,
 }
 
 impl Wing {
-    /// Combined evaluator: returns CL and CD (useful for UI, logging, stability).
+    /// Mean aerodynamic chord (m).
+    #[inline]
+    pub fn chord(&self) -> f64 {
+        self.area / self.span.max(1e-6)
+    }
+
+    /// Combined evaluator: returns CL, CD and CM (useful for UI, logging, stability).
     #[inline]
     pub fn eval_coeffs(&self, aoa: f64, flow: Flow) -> WingCoeffs {
         // Geometry-derived constants
@@ -197,10 +409,14 @@ impl Wing {
         let cla = cla_inc * comp_gain;
 
         // Controls
-        let (dcl, dcd0) = if let Some(c) = self.control {
-            (c.a_delta * c.delta, c.dcd0_delta * c.delta.abs())
+        let (dcl, dcd0, cm) = if let Some(c) = self.control {
+            (
+                c.a_delta * c.delta,
+                c.dcd0_delta * c.delta.abs(),
+                c.m_delta * c.delta,
+            )
         } else {
-            (0.0, 0.0)
+            (0.0, 0.0, 0.0)
         };
 
         // Linear CL and smooth stall cap (~3° band)
@@ -225,7 +441,7 @@ impl Wing {
         };
         let cd = cd0 + k * cl * cl + k_stall * (cl_lin - cl).abs() + cd_wave;
 
-        WingCoeffs { cl, cd }
+        WingCoeffs { cl, cd, cm }
     }
 
     /// Evaluate the forces on this wing, given the angle of attack and airflow.
@@ -236,6 +452,7 @@ impl Wing {
         WingForces {
             lift: c.cl * qS,
             drag: c.cd * qS,
+            moment: c.cm * qS * self.chord(),
         }
     }
 }
@@ -256,7 +473,8 @@ fn soft_clip(x: f64, xmin: f64, xmax: f64, width: f64) -> f64 {
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ControlSurface {
-    /// Commanded deflection (rad).
+    /// Current deflection (rad), driven by the actuator.
+    #[serde(default)]
     pub delta: f64,
     /// Lift increment per rad of deflection (ΔCL = a_delta * delta).
     pub a_delta: f64,
@@ -264,11 +482,58 @@ pub struct ControlSurface {
     pub dcd0_delta: f64,
     /// Pitching-moment change per rad (ΔCM = m_delta * delta).
     pub m_delta: f64,
+
+    #[serde(default)]
+    pub actuator: Actuator,
+}
+
+impl ControlSurface {
+    /// Deflection (rad) commanded by a steering input, given as pitch, yaw and roll in `[-1, 1]`.
+    pub fn commanded_delta(&self, steering: DVec3) -> f64 {
+        let max = self.actuator.max_delta.abs();
+        self.actuator.gains.dot(steering).clamp(-max, max)
+    }
+
+    /// Moves the deflection towards the commanded one, no faster than the actuator rate allows.
+    pub fn actuate(&mut self, steering: DVec3, dt: f64) {
+        let max_step = self.actuator.rate.abs() * dt;
+        let step = (self.commanded_delta(steering) - self.delta).clamp(-max_step, max_step);
+        self.delta += step;
+    }
+}
+
+/// How a control surface follows the vessel's steering.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Actuator {
+    /// Deflection (rad) per unit of pitch, yaw and roll input. Signs should be chosen so that
+    /// positive input rotates the vessel positively about the corresponding axis.
+    pub gains: DVec3,
+    /// Deflection limit (rad), in either direction.
+    pub max_delta: f64,
+    /// Maximum deflection rate (rad/s).
+    pub rate: f64,
+}
+
+impl Default for Actuator {
+    fn default() -> Self {
+        Self {
+            gains: DVec3::ZERO,
+            max_delta: 20.0_f64.to_radians(),
+            rate: 60.0_f64.to_radians(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::physics::aerodynamics::aero_model::{Flow, Wing};
+    use bevy::math::DVec3;
+
+    use crate::physics::aerodynamics::{
+        AeroEnv,
+        aero_model::{Actuator, AeroModel, ControlSurface, Flow, MainBodyModel, Wing},
+    };
+    use crate::precision::PreciseTransform;
 
     #[test]
     fn simple_wing() {
@@ -284,9 +549,117 @@ mod tests {
                 Flow {
                     mach: 0.8,
                     q: 1000.0,
+                    reynolds_per_m: 1e6,
                 },
             );
             eprintln!("{:.4} / {:.4}", forces.lift, forces.drag)
         }
     }
+
+    #[test]
+    fn body_drag_depends_on_orientation() {
+        let env = AeroEnv {
+            density: 1.2,
+            speed_of_sound: 340.0,
+            viscosity: 1.8e-5,
+            ..Default::default()
+        };
+        let drag = |model: &MainBodyModel, dir: DVec3| {
+            model.eval(dir * 100.0, DVec3::ZERO, &env).force.length()
+        };
+
+        // a long cylinder is much draggier broadside than end-on
+        let cylinder = MainBodyModel::Cylinder {
+            radius: 1.0,
+            length: 10.0,
+        };
+        assert!(drag(&cylinder, DVec3::X) > 5.0 * drag(&cylinder, DVec3::Z));
+
+        // a nose cone is draggier going backwards
+        let cone = MainBodyModel::Nosecone {
+            radius: 1.0,
+            length: 3.0,
+        };
+        assert!(drag(&cone, DVec3::Z) > 2.0 * drag(&cone, -DVec3::Z));
+
+        // two cubes stacked along Z shield each other's touching faces
+        let single = MainBodyModel::cuboid(DVec3::ONE);
+        let stacked = MainBodyModel::Composite(MainBodyModel::shielded_boxes(&[
+            (DVec3::new(0.0, 0.0, -0.5), DVec3::ONE),
+            (DVec3::new(0.0, 0.0, 0.5), DVec3::ONE),
+        ]));
+        let ratio_z = drag(&stacked, DVec3::Z) / drag(&single, DVec3::Z);
+        let ratio_x = drag(&stacked, DVec3::X) / drag(&single, DVec3::X);
+        assert!((1.0..1.05).contains(&ratio_z), "{ratio_z}");
+        assert!((1.95..2.0).contains(&ratio_x), "{ratio_x}");
+    }
+
+    #[test]
+    fn control_surface_actuation() {
+        let mut control = ControlSurface {
+            delta: 0.0,
+            a_delta: 3.0,
+            dcd0_delta: 0.01,
+            m_delta: -0.5,
+            actuator: Actuator {
+                gains: DVec3::new(-0.5, 0.0, 0.2),
+                max_delta: 0.3,
+                rate: 1.0,
+            },
+        };
+        // rate-limited
+        control.actuate(DVec3::new(-1.0, 0.0, 0.0), 0.1);
+        assert!((control.delta - 0.1).abs() < 1e-12);
+        // saturates at the deflection limit
+        for _ in 0..10 {
+            control.actuate(DVec3::new(-1.0, 0.0, 1.0), 0.1);
+        }
+        assert!((control.delta - 0.3).abs() < 1e-12);
+
+        let wing = Wing {
+            area: 2.0,
+            span: 4.0,
+            details: Default::default(),
+            control: Some(control),
+        };
+        let flow = Flow {
+            mach: 0.2,
+            q: 1000.0,
+            reynolds_per_m: 1e6,
+        };
+        let forces = wing.eval_forces(0.0, flow);
+        assert!(forces.lift > 0.0);
+        assert!((forces.moment - (-0.5 * 0.3 * 1000.0 * 2.0 * 0.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn wings_damp_rotation() {
+        let env = AeroEnv {
+            density: 1.2,
+            speed_of_sound: 340.0,
+            viscosity: 1.8e-5,
+            ..Default::default()
+        };
+        // a lone wing 5 m behind the centre of mass
+        let model = AeroModel {
+            main: MainBodyModel::Composite(vec![]),
+            wings: vec![(
+                PreciseTransform {
+                    translation_mm: bevy::math::I64Vec3::new(0, 0, 5_000),
+                    ..Default::default()
+                },
+                Wing {
+                    area: 2.0,
+                    span: 4.0,
+                    details: Default::default(),
+                    control: None,
+                },
+            )],
+        };
+        // yawing in still air, the wing moves along +X at ω × r; its drag must oppose that and slow the spin down
+        let angvel = DVec3::Y;
+        let out = model.relative_force(DVec3::ZERO, angvel, &env);
+        assert!(out.force.x < 0.0, "{:?}", out.force);
+        assert!(out.torque.dot(angvel) < 0.0, "{:?}", out.torque);
+    }
 }
@@ -0,0 +1,604 @@
+use anyhow::Context;
+use bevy::math::DVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    orrery::de_distance,
+    physics::aerodynamics::{WindCfg, WindField},
+};
+
+/// Universal gas constant, in J mol⁻¹ K⁻¹.
+const R_UNIV: f64 = 8.314_462_618;
+
+/// The `atmosphere` section of a body in the star system configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AtmosphereCfg {
+    /// Altitude (m) of the profile's reference level above the body's nominal radius.
+    #[serde(deserialize_with = "de_distance", default)]
+    pub datum: f64,
+    /// Altitude (m) above the nominal radius where the atmosphere ends; above it is vacuum.
+    #[serde(deserialize_with = "de_distance")]
+    pub top: f64,
+    /// Mean molar mass of the gas, in kg mol⁻¹.
+    pub molar_mass: f64,
+    /// Ratio of specific heats.
+    #[serde(default = "default_gamma")]
+    pub gamma: f64,
+    #[serde(flatten)]
+    pub profile: ProfileCfg,
+    /// Winds and weather; without them, the atmosphere is at rest.
+    #[serde(default)]
+    pub wind: Option<WindCfg>,
+}
+
+fn default_gamma() -> f64 {
+    1.4
+}
+
+/// How pressure and temperature vary with altitude.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "model")]
+pub enum ProfileCfg {
+    /// The hand-tuned reference profile of Pannea.
+    Pannea,
+    /// A stack of constant lapse-rate layers, as in the standard atmospheres.
+    Layered(LayeredAtmosphere),
+    /// A profile tabulated against height, inline or from a data file.
+    Table(TableCfg),
+}
+
+/// Local conditions of an atmosphere.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AtmosphereSample {
+    pub pressure: f64,    // Pa
+    pub density: f64,     // kg m⁻³
+    pub temperature: f64, // K
+    pub opacity: f64,     // 0‥1  (fraction of sunlight transmitted)
+    pub speed_of_sound: f64,
+}
+
+impl AtmosphereSample {
+    pub const VACUUM: Self = Self {
+        pressure: 0.0,
+        density: 0.0,
+        temperature: 0.0,
+        opacity: 1.0,
+        speed_of_sound: 0.0,
+    };
+}
+
+/// A pressure and temperature profile of an atmosphere.
+pub trait Atmosphere: Send + Sync + 'static {
+    /// Samples the atmosphere at a height (m) above its reference level. The speed of sound is left to the caller.
+    fn sample(&self, height: f64, r_specific: f64) -> AtmosphereSample;
+}
+
+/// The atmosphere of a celestial body.
+pub struct BodyAtmosphere {
+    datum: f64,
+    top: f64,
+    gamma: f64,
+    r_specific: f64,
+    profile: Box<dyn Atmosphere>,
+    wind: Option<WindField>,
+}
+
+impl BodyAtmosphere {
+    /// Builds an atmosphere from its configuration, given the gravity (m s⁻²) at the reference level.
+    pub fn new(cfg: &AtmosphereCfg, gravity: f64) -> anyhow::Result<Self> {
+        let profile: Box<dyn Atmosphere> = match &cfg.profile {
+            ProfileCfg::Pannea => Box::new(PanneaAtmosphere),
+            ProfileCfg::Layered(layered) => Box::new(LayeredAtmosphere {
+                gravity: layered.gravity.or(Some(gravity)),
+                ..layered.clone()
+            }),
+            ProfileCfg::Table(table) => Box::new(TabulatedAtmosphere::new(table)?),
+        };
+        Ok(Self {
+            datum: cfg.datum,
+            top: cfg.top,
+            gamma: cfg.gamma,
+            r_specific: R_UNIV / cfg.molar_mass,
+            profile,
+            wind: cfg.wind.as_ref().map(WindField::new),
+        })
+    }
+
+    /// Samples the atmosphere and its weather at a position (m) in the body's rotating frame, whose Z axis is the
+    /// spin axis, at a time (s). Returns the local conditions and the wind (m/s) in that frame.
+    pub fn sample_weather(
+        &self,
+        position: DVec3,
+        altitude: f64,
+        time: f64,
+    ) -> (AtmosphereSample, DVec3) {
+        if altitude > self.top {
+            return (AtmosphereSample::VACUUM, DVec3::ZERO);
+        }
+        let height = altitude - self.datum;
+        let mut sample = self.profile.sample(height, self.r_specific);
+        let wind = self.wind.as_ref().map_or(DVec3::ZERO, |wind| {
+            wind.apply(position, height, time, &mut sample)
+        });
+        self.finish(&mut sample);
+        (sample, wind)
+    }
+
+    fn finish(&self, sample: &mut AtmosphereSample) {
+        sample.speed_of_sound = (self.gamma * self.r_specific * sample.temperature)
+            .max(0.0)
+            .sqrt();
+    }
+}
+
+/// An atmosphere made of layers with a constant temperature lapse rate, in hydrostatic equilibrium.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayeredAtmosphere {
+    /// Pressure (Pa) at the reference level.
+    pub pressure: f64,
+    /// Temperature (K) at the reference level.
+    pub temperature: f64,
+    /// Gravity (m s⁻²), if it should differ from the body's gravity at the reference level.
+    #[serde(default)]
+    pub gravity: Option<f64>,
+    /// Extinction coefficient (Pa⁻¹) of the toy opacity model `opacity = exp(−k · P)`.
+    #[serde(default)]
+    pub extinction: f64,
+    /// Layers, by increasing base height. The first one also extends below the reference level; with none, the
+    /// atmosphere is isothermal.
+    pub layers: Vec<LapseLayer>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LapseLayer {
+    /// Height (m) of the layer's base above the reference level.
+    #[serde(deserialize_with = "de_distance", default)]
+    pub base: f64,
+    /// Temperature decrease with height, in K m⁻¹.
+    #[serde(default)]
+    pub lapse: f64,
+}
+
+impl Atmosphere for LayeredAtmosphere {
+    fn sample(&self, height: f64, r_specific: f64) -> AtmosphereSample {
+        let g = self.gravity.unwrap_or(0.0);
+        let mut temperature = self.temperature;
+        let mut pressure = self.pressure;
+        let mut base = 0.0;
+
+        let isothermal = [LapseLayer {
+            base: 0.0,
+            lapse: 0.0,
+        }];
+        let layers = if self.layers.is_empty() {
+            &isothermal[..]
+        } else {
+            &self.layers
+        };
+        let mut layers = layers.iter().peekable();
+        while let Some(layer) = layers.next() {
+            // the first layer starts at the reference level, whatever its configured base
+            let top = match layers.peek() {
+                Some(next) if next.base < height => next.base,
+                _ => height,
+            };
+            let dh = top - base;
+            let t_top = (temperature - layer.lapse * dh).max(1.0);
+            pressure *= if layer.lapse == 0.0 {
+                (-g * dh / (r_specific * temperature)).exp()
+            } else {
+                (t_top / temperature).powf(g / (r_specific * layer.lapse))
+            };
+            temperature = t_top;
+            base = top;
+            if top == height {
+                break;
+            }
+        }
+
+        AtmosphereSample {
+            pressure,
+            density: pressure / (r_specific * temperature),
+            temperature,
+            opacity: (-self.extinction * pressure).exp().clamp(0.0, 1.0),
+            speed_of_sound: 0.0,
+        }
+    }
+}
+
+/// An atmosphere profile tabulated against height above the reference level.
+///
+/// Columns may be given inline as arrays, or read from a CSV `file` whose header names the columns. Only `height`,
+/// `pressure` and `temperature` are required; a missing `density` is derived from the ideal gas law, and a missing
+/// `opacity` means a fully transparent atmosphere.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TableCfg {
+    /// Path of a CSV file holding the columns, relative to the star system configuration.
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub height: Vec<f64>,
+    #[serde(default)]
+    pub pressure: Vec<f64>,
+    #[serde(default)]
+    pub temperature: Vec<f64>,
+    #[serde(default)]
+    pub density: Vec<f64>,
+    #[serde(default)]
+    pub opacity: Vec<f64>,
+    /// How the profile continues below the lowest row.
+    #[serde(default)]
+    pub below: Extrapolation,
+    /// How the profile continues above the highest row.
+    #[serde(default)]
+    pub above: Extrapolation,
+}
+
+/// How a tabulated profile continues past its ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Extrapolation {
+    /// Hold the values at the end of the table.
+    Clamp,
+    /// Continue with the slope at the end of the table. Pressure and density are extrapolated in log space, so
+    /// they keep decaying exponentially.
+    #[default]
+    Linear,
+}
+
+impl TableCfg {
+    /// Fills in the columns from CSV text with a header row, replacing any given inline. Blank lines and lines
+    /// starting with `#` are skipped.
+    pub fn read_csv(&mut self, text: &str) -> anyhow::Result<()> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let (_, header) = lines.next().context("missing header")?;
+        const NAMES: [&str; 5] = ["height", "pressure", "temperature", "density", "opacity"];
+        let mut indices = vec![];
+        for name in header.split(',').map(str::trim) {
+            let idx = NAMES
+                .iter()
+                .position(|n| *n == name)
+                .with_context(|| format!("unknown column {name:?}"))?;
+            if indices.contains(&idx) {
+                anyhow::bail!("duplicate column {name:?}");
+            }
+            indices.push(idx);
+        }
+
+        let mut columns: [Vec<f64>; 5] = Default::default();
+        for (line_idx, line) in lines {
+            let values = line.split(',').map(str::trim).collect::<Vec<_>>();
+            if values.len() != indices.len() {
+                anyhow::bail!("line {}: expected {} values", line_idx + 1, indices.len());
+            }
+            for (&idx, value) in indices.iter().zip(values) {
+                columns[idx].push(
+                    value
+                        .parse()
+                        .with_context(|| format!("line {}: bad number {value:?}", line_idx + 1))?,
+                );
+            }
+        }
+        [
+            self.height,
+            self.pressure,
+            self.temperature,
+            self.density,
+            self.opacity,
+        ] = columns;
+        Ok(())
+    }
+}
+
+/// A tabulated profile, interpolated with monotone cubic splines.
+pub struct TabulatedAtmosphere {
+    log_pressure: MonotoneCubic,
+    temperature: MonotoneCubic,
+    log_density: Option<MonotoneCubic>,
+    opacity: Option<MonotoneCubic>,
+    below: Extrapolation,
+    above: Extrapolation,
+}
+
+impl TabulatedAtmosphere {
+    pub fn new(cfg: &TableCfg) -> anyhow::Result<Self> {
+        let height = &cfg.height;
+        if height.len() < 2 {
+            anyhow::bail!("an atmosphere table needs at least two rows");
+        }
+        if height.windows(2).any(|w| w[0] >= w[1]) {
+            anyhow::bail!("atmosphere table heights must be strictly increasing");
+        }
+        let column = |name: &str, values: &[f64], log: bool| -> anyhow::Result<MonotoneCubic> {
+            if values.len() != height.len() {
+                anyhow::bail!(
+                    "atmosphere table column {name} has {} rows, expected {}",
+                    values.len(),
+                    height.len()
+                );
+            }
+            if log {
+                if values.iter().any(|v| *v <= 0.0) {
+                    anyhow::bail!("atmosphere table column {name} must be positive");
+                }
+                Ok(MonotoneCubic::new(
+                    height,
+                    &values.iter().map(|v| v.ln()).collect::<Vec<_>>(),
+                ))
+            } else {
+                Ok(MonotoneCubic::new(height, values))
+            }
+        };
+        let optional = |name: &str, values: &[f64], log: bool| {
+            (!values.is_empty())
+                .then(|| column(name, values, log))
+                .transpose()
+        };
+        Ok(Self {
+            log_pressure: column("pressure", &cfg.pressure, true)?,
+            temperature: column("temperature", &cfg.temperature, false)?,
+            log_density: optional("density", &cfg.density, true)?,
+            opacity: optional("opacity", &cfg.opacity, false)?,
+            below: cfg.below,
+            above: cfg.above,
+        })
+    }
+}
+
+impl Atmosphere for TabulatedAtmosphere {
+    fn sample(&self, height: f64, r_specific: f64) -> AtmosphereSample {
+        let eval = |spline: &MonotoneCubic| spline.eval(height, self.below, self.above);
+        let pressure = eval(&self.log_pressure).exp();
+        let temperature = eval(&self.temperature).max(1.0);
+        AtmosphereSample {
+            pressure,
+            density: self
+                .log_density
+                .as_ref()
+                .map_or(pressure / (r_specific * temperature), |d| eval(d).exp()),
+            temperature,
+            opacity: self
+                .opacity
+                .as_ref()
+                .map_or(1.0, |o| eval(o).clamp(0.0, 1.0)),
+            speed_of_sound: 0.0,
+        }
+    }
+}
+
+/// A monotone cubic Hermite spline (Fritsch–Carlson), which does not overshoot between samples.
+struct MonotoneCubic {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    slopes: Vec<f64>,
+}
+
+impl MonotoneCubic {
+    /// Builds a spline through at least two points, with strictly increasing `xs`.
+    fn new(xs: &[f64], ys: &[f64]) -> Self {
+        let n = xs.len();
+        let secants = (0..n - 1)
+            .map(|k| (ys[k + 1] - ys[k]) / (xs[k + 1] - xs[k]))
+            .collect::<Vec<_>>();
+        let mut slopes = vec![0.0; n];
+        slopes[0] = secants[0];
+        slopes[n - 1] = secants[n - 2];
+        for k in 1..n - 1 {
+            if secants[k - 1] * secants[k] > 0.0 {
+                slopes[k] = (secants[k - 1] + secants[k]) / 2.0;
+            }
+        }
+        for (k, &secant) in secants.iter().enumerate() {
+            if secant == 0.0 {
+                slopes[k] = 0.0;
+                slopes[k + 1] = 0.0;
+                continue;
+            }
+            let a = slopes[k] / secant;
+            let b = slopes[k + 1] / secant;
+            let norm = a * a + b * b;
+            if norm > 9.0 {
+                let t = 3.0 / norm.sqrt();
+                slopes[k] = t * a * secant;
+                slopes[k + 1] = t * b * secant;
+            }
+        }
+        Self {
+            xs: xs.to_vec(),
+            ys: ys.to_vec(),
+            slopes,
+        }
+    }
+
+    fn eval(&self, x: f64, below: Extrapolation, above: Extrapolation) -> f64 {
+        let n = self.xs.len();
+        if x <= self.xs[0] {
+            return match below {
+                Extrapolation::Clamp => self.ys[0],
+                Extrapolation::Linear => self.ys[0] + self.slopes[0] * (x - self.xs[0]),
+            };
+        }
+        if x >= self.xs[n - 1] {
+            return match above {
+                Extrapolation::Clamp => self.ys[n - 1],
+                Extrapolation::Linear => self.ys[n - 1] + self.slopes[n - 1] * (x - self.xs[n - 1]),
+            };
+        }
+        let k = self.xs.partition_point(|&xk| xk <= x) - 1;
+        let h = self.xs[k + 1] - self.xs[k];
+        let t = (x - self.xs[k]) / h;
+        let t2 = t * t;
+        let t3 = t2 * t;
+        (2.0 * t3 - 3.0 * t2 + 1.0) * self.ys[k]
+            + (t3 - 2.0 * t2 + t) * h * self.slopes[k]
+            + (-2.0 * t3 + 3.0 * t2) * self.ys[k + 1]
+            + (t3 - t2) * h * self.slopes[k + 1]
+    }
+}
+
+/// Simple “standard-atmosphere” model for the sky-world **Pannea**.
+///
+/// Heights are metres **above the 1-bar layer** (positive = higher, negative = deeper).
+/// The 1-bar datum is ≈ 285 K and ρ ≈ 1.39 kg m⁻³.
+///
+/// The model is piece-wise:
+/// * Troposphere: linear lapse-rate **L = 6 K km⁻¹** down to the 1 GPa “death-zone”
+///
+/// * Tropopause: at **hₜ = 10 000 m** the temperature bottoms at **Tₜ = 225 K**
+///   Above this, an isothermal stratosphere (≈ 220 K) is assumed.
+///
+/// * Pressure in the lapse region uses the standard ideal-gas/hydrostatic relation;
+///   above the tropopause, pressure decays exponentially with scale-height
+///   **H_iso = Rᵣₛ·T_iso / g ≈ 5 500 m**.
+///
+/// * “Opacity” is a toy optical-depth model:
+///   `opacity = exp( −k · P )` with *k* = 1.5 × 10⁻⁵ Pa⁻¹.
+///   → About **22 %** of solar flux reaches the 1-bar deck (matching the
+///   “bright overcast” description) and ≳80 % reaches the top of broken-cloud
+///   layers at ~0.4 bar.
+///
+/// > **Caveat** Real weather on Pannea varies ±20 K and ±30 % pressure inside
+/// > cyclones; this routine is only a background reference.
+pub struct PanneaAtmosphere;
+
+impl Atmosphere for PanneaAtmosphere {
+    fn sample(&self, height: f64, r_spec: f64) -> AtmosphereSample {
+        // ---------- constants ----------
+        const G: f64 = 10.0; // m s⁻²  (surface gravity)
+        const T0: f64 = 285.0; // K  (1-bar layer)
+        const P0: f64 = 1.0e5; // Pa
+        const LAPSE: f64 = 0.006; // K m⁻¹ (6 K km⁻¹)
+        const HTROP: f64 = 10_000.0; // m  (tropopause above 1 bar)
+        const T_TROP: f64 = 225.0; // K  bottom-out temperature
+        const T_ISO: f64 = 220.0; // K  isothermal stratosphere
+        const K_OPA: f64 = 1.5e-5; // Pa⁻¹  (opacity coefficient)
+
+        // exponent used in the Poisson formula (g / (R*L))
+        let exponent = G / (r_spec * LAPSE);
+
+        // ---------- temperature profile ----------
+        let (temp, pressure) = if height <= HTROP {
+            // Linear lapse region (handles negative altitude too)
+            let t = T0 - LAPSE * height;
+            let t_clamped = t.max(150.0); // keep numeric sanity deep down
+            let p = P0 * (t_clamped / T0).powf(exponent);
+            (t_clamped, p)
+        } else {
+            // Isothermal upper layer
+            // First: conditions at tropopause
+            let p_trop = P0 * (T_TROP / T0).powf(exponent);
+            let h = height - HTROP;
+            let h_scale = (r_spec * T_ISO) / G; // ≈ 5.5 km
+            let p = p_trop * (-h / h_scale).exp();
+            (T_ISO, p)
+        };
+
+        // ---------- density ----------
+        let density = pressure / (r_spec * temp);
+
+        // ---------- toy optical-depth / opacity ----------
+        let opacity = (-K_OPA * pressure).exp().clamp(0.0, 1.0);
+
+        AtmosphereSample {
+            pressure,
+            density,
+            temperature: temp,
+            opacity,
+            speed_of_sound: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layered_standard_atmosphere() {
+        let cfg: AtmosphereCfg = toml::from_str(
+            r#"
+            top = "100 km"
+            molar_mass = 0.028964
+            model = "layered"
+            pressure = 101325.0
+            temperature = 288.15
+            layers = [
+                { base = 0, lapse = 0.0065 },
+                { base = "11 km", lapse = 0.0 },
+                { base = "20 km", lapse = -0.001 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let atm = BodyAtmosphere::new(&cfg, 9.80665).unwrap();
+        // without winds, the weather is the same everywhere
+        let sample = |altitude| atm.sample_weather(DVec3::ZERO, altitude, 0.0).0;
+
+        let sea = sample(0.0);
+        assert!((sea.density - 1.225).abs() < 1e-3);
+        assert!((sea.speed_of_sound - 340.3).abs() < 0.1);
+
+        let trop = sample(11_000.0);
+        assert!((trop.temperature - 216.65).abs() < 1e-9);
+        assert!((trop.pressure - 22_632.0).abs() < 5.0);
+        let strat = sample(20_000.0);
+        assert!((strat.pressure - 5_474.9).abs() < 2.0);
+        let upper = sample(25_000.0);
+        assert!((upper.temperature - 221.65).abs() < 1e-9);
+
+        // extrapolated downwards with the first layer's lapse rate
+        assert!(sample(-500.0).pressure > sea.pressure);
+        assert_eq!(sample(100_001.0), AtmosphereSample::VACUUM);
+    }
+
+    #[test]
+    fn tabulated_profile() {
+        let mut table = TableCfg {
+            below: Extrapolation::Clamp,
+            ..Default::default()
+        };
+        table
+            .read_csv(
+                "# a made-up profile
+                height, pressure, temperature, opacity
+                0, 100000, 285, 0.2
+                5000, 50000, 255, 0.5
+                10000, 20000, 225, 0.8
+                20000, 5000, 225, 0.95",
+            )
+            .unwrap();
+        let atm = TabulatedAtmosphere::new(&table).unwrap();
+        let r_specific = 252.0;
+
+        // passes through the rows
+        let row = atm.sample(5000.0, r_specific);
+        assert!((row.pressure - 50_000.0).abs() < 1e-6);
+        assert!((row.density - 50_000.0 / (r_specific * 255.0)).abs() < 1e-9);
+
+        // no overshoot across the flat temperature segment
+        for h in (10_000..=20_000).step_by(500) {
+            let t = atm.sample(h as f64, r_specific).temperature;
+            assert!((t - 225.0).abs() < 1e-9);
+        }
+        // monotone between rows
+        let mut last = f64::INFINITY;
+        for h in (0..=20_000).step_by(100) {
+            let p = atm.sample(h as f64, r_specific).pressure;
+            assert!(p < last);
+            last = p;
+        }
+
+        // clamped below, decaying exponentially above
+        assert_eq!(atm.sample(-1000.0, r_specific), atm.sample(0.0, r_specific));
+        let p30 = atm.sample(30_000.0, r_specific).pressure;
+        let p40 = atm.sample(40_000.0, r_specific).pressure;
+        assert!(p30 < 5_000.0 && (p40 / p30 - p30 / 5_000.0).abs() < 1e-9);
+
+        table.temperature.pop();
+        assert!(TabulatedAtmosphere::new(&table).is_err());
+    }
+}
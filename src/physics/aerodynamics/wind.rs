@@ -0,0 +1,362 @@
+use std::f64::consts::PI;
+
+use bevy::math::DVec3;
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{orrery::de_distance, physics::aerodynamics::AtmosphereSample};
+
+/// The `wind` section of a body's atmosphere. Heights are above the atmosphere's reference level.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WindCfg {
+    /// Seed of the turbulence and storm cells, so that the weather is the same on every run.
+    #[serde(default)]
+    pub seed: u64,
+    /// Eastward wind (m/s) by latitude (°), interpolated linearly between bands.
+    #[serde(default)]
+    pub zonal: Vec<ZonalBand>,
+    /// Scale factor of the zonal wind by height, interpolated linearly between levels. Defaults to 1 everywhere.
+    #[serde(default)]
+    pub zonal_profile: Vec<ZonalLevel>,
+    #[serde(default)]
+    pub turbulence: Option<TurbulenceCfg>,
+    #[serde(default)]
+    pub storms: Option<StormCfg>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ZonalBand {
+    /// Latitude, in degrees.
+    pub latitude: f64,
+    /// Eastward wind speed, in m/s.
+    pub speed: f64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ZonalLevel {
+    #[serde(deserialize_with = "de_distance")]
+    pub height: f64,
+    pub factor: f64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TurbulenceCfg {
+    /// Typical gust speed, in m/s.
+    pub intensity: f64,
+    /// Size of the largest eddies, in meters.
+    #[serde(deserialize_with = "de_distance")]
+    pub length_scale: f64,
+    /// Time for the gusts to change, in seconds.
+    pub time_scale: f64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct StormCfg {
+    /// Number of storms active at any time.
+    pub count: usize,
+    /// Typical radius of a storm, in meters.
+    #[serde(deserialize_with = "de_distance")]
+    pub radius: f64,
+    /// Height of the storm tops, above which storms fade out.
+    #[serde(deserialize_with = "de_distance")]
+    pub height: f64,
+    /// Peak rotational wind speed, in m/s.
+    pub max_wind: f64,
+    /// Fraction by which pressure drops in the eye.
+    pub pressure_drop: f64,
+    /// Temperature drop in the eye, in K.
+    pub temperature_drop: f64,
+    /// Lifetime of a storm, in seconds.
+    pub lifetime: f64,
+    /// Storms form between these latitudes (°) north and south.
+    #[serde(default = "default_storm_latitude")]
+    pub max_latitude: f64,
+}
+
+fn default_storm_latitude() -> f64 {
+    60.0
+}
+
+/// The weather of a body: winds, gusts and storms, in the body's rotating frame.
+pub struct WindField {
+    cfg: WindCfg,
+    perm: [u8; 256],
+    gusts: [DVec3; 256],
+}
+
+impl WindField {
+    pub fn new(cfg: &WindCfg) -> Self {
+        let mut cfg = cfg.clone();
+        cfg.zonal
+            .sort_unstable_by(|a, b| a.latitude.total_cmp(&b.latitude));
+        cfg.zonal_profile
+            .sort_unstable_by(|a, b| a.height.total_cmp(&b.height));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(cfg.seed);
+        let mut perm = [0u8; 256];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+        perm.shuffle(&mut rng);
+        let gusts = std::array::from_fn(|_| {
+            // each component has unit variance
+            DVec3::new(
+                rng.random_range(-1.0..1.0),
+                rng.random_range(-1.0..1.0),
+                rng.random_range(-1.0..1.0),
+            ) * 3.0_f64.sqrt()
+        });
+        Self { cfg, perm, gusts }
+    }
+
+    /// Computes the wind (m/s) at a position (m) in the body's rotating frame, whose Z axis is the spin axis, and
+    /// perturbs the local conditions for any storm there. `height` is above the atmosphere's reference level and
+    /// `time` is in seconds.
+    pub fn apply(
+        &self,
+        position: DVec3,
+        height: f64,
+        time: f64,
+        sample: &mut AtmosphereSample,
+    ) -> DVec3 {
+        let up = position.normalize_or(DVec3::Z);
+        let east = DVec3::Z.cross(up).normalize_or(DVec3::X);
+        let latitude = up.z.clamp(-1.0, 1.0).asin().to_degrees();
+
+        let mut wind = east * self.zonal_speed(latitude, height);
+        if let Some(turbulence) = &self.cfg.turbulence {
+            wind += self.gust(
+                position / turbulence.length_scale,
+                time / turbulence.time_scale,
+            ) * turbulence.intensity;
+        }
+        if let Some(storms) = &self.cfg.storms {
+            wind += self.storms(storms, position, up, height, time, sample);
+        }
+        wind
+    }
+
+    fn zonal_speed(&self, latitude: f64, height: f64) -> f64 {
+        let speed = interp(
+            self.cfg
+                .zonal
+                .iter()
+                .map(|band| (band.latitude, band.speed)),
+            latitude,
+        )
+        .unwrap_or(0.0);
+        let factor = interp(
+            self.cfg
+                .zonal_profile
+                .iter()
+                .map(|level| (level.height, level.factor)),
+            height,
+        )
+        .unwrap_or(1.0);
+        speed * factor
+    }
+
+    /// Smooth value noise over space and time, with two octaves.
+    fn gust(&self, point: DVec3, time: f64) -> DVec3 {
+        self.value_noise(point, time) * 0.8 + self.value_noise(point * 2.0 + 17.0, time * 2.0) * 0.4
+    }
+
+    fn value_noise(&self, point: DVec3, time: f64) -> DVec3 {
+        let coords = [point.x, point.y, point.z, time];
+        let base = coords.map(|c| c.floor());
+        let weights = coords
+            .iter()
+            .zip(base)
+            .map(|(c, b)| smooth(c - b))
+            .collect::<Vec<_>>();
+        let mut sum = DVec3::ZERO;
+        for corner in 0..16 {
+            let mut weight = 1.0;
+            let mut hash = 0u8;
+            for (axis, b) in base.iter().enumerate() {
+                let bit = (corner >> axis) & 1;
+                weight *= if bit == 1 {
+                    weights[axis]
+                } else {
+                    1.0 - weights[axis]
+                };
+                // lattice coordinates wrap around every 256 cells
+                let cell = (*b as i64 + bit as i64).rem_euclid(256) as u8;
+                hash = self.perm[hash.wrapping_add(cell) as usize];
+            }
+            sum += self.gusts[hash as usize] * weight;
+        }
+        sum
+    }
+
+    fn storms(
+        &self,
+        cfg: &StormCfg,
+        position: DVec3,
+        up: DVec3,
+        height: f64,
+        time: f64,
+        sample: &mut AtmosphereSample,
+    ) -> DVec3 {
+        let vertical = if height <= cfg.height {
+            1.0
+        } else {
+            (-(height - cfg.height) / (0.2 * cfg.height).max(1.0)).exp()
+        };
+        let mut wind = DVec3::ZERO;
+        let mut pressure_factor = 1.0;
+        let mut temperature_drop = 0.0;
+        for cell in self.storm_cells(cfg, time) {
+            let distance = position.length() * up.angle_between(cell.center);
+            let r = distance / cell.radius;
+            if r > 4.0 {
+                continue;
+            }
+            let strength = cell.strength * vertical;
+            let core = (-r * r).exp();
+            pressure_factor *= 1.0 - cfg.pressure_drop * strength * core;
+            temperature_drop += cfg.temperature_drop * strength * core;
+
+            // cyclonic rotation: anticlockwise seen from above in the north, clockwise in the south
+            let outward = (up - cell.center).reject_from(up).normalize_or_zero();
+            let spin = cell.center.z.signum();
+            let speed = cfg.max_wind * strength * r * (0.5 * (1.0 - r * r)).exp();
+            wind += up.cross(outward) * speed * spin;
+        }
+        if pressure_factor != 1.0 {
+            let temperature = (sample.temperature - temperature_drop).max(1.0);
+            sample.density *= pressure_factor * sample.temperature / temperature;
+            sample.pressure *= pressure_factor;
+            sample.temperature = temperature;
+        }
+        wind
+    }
+
+    /// The storms active at a time. Each of the `count` slots spawns a storm at a random place once per lifetime,
+    /// staggered so that storms come and go one at a time.
+    fn storm_cells(&self, cfg: &StormCfg, time: f64) -> impl Iterator<Item = StormCell> {
+        let seed = self.cfg.seed;
+        let max_sin_lat = cfg.max_latitude.to_radians().sin();
+        let lifetime = cfg.lifetime.max(1.0);
+        let count = cfg.count;
+        let radius = cfg.radius;
+        (0..count).map(move |slot| {
+            let age = time / lifetime + slot as f64 / count as f64;
+            let generation = age.floor();
+            let phase = age - generation;
+            let mut rng = ChaCha8Rng::seed_from_u64(
+                seed ^ (slot as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                    ^ (generation as i64 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f),
+            );
+            let sin_lat: f64 = rng.random_range(-max_sin_lat..=max_sin_lat);
+            let cos_lat = (1.0 - sin_lat * sin_lat).sqrt();
+            let longitude: f64 = rng.random_range(0.0..2.0 * PI);
+            StormCell {
+                center: DVec3::new(
+                    cos_lat * longitude.cos(),
+                    cos_lat * longitude.sin(),
+                    sin_lat,
+                ),
+                radius: radius * rng.random_range(0.5..1.5),
+                strength: (PI * phase).sin(),
+            }
+        })
+    }
+}
+
+struct StormCell {
+    /// Direction of the eye from the body's center.
+    center: DVec3,
+    radius: f64,
+    /// From 0 as the storm forms, to 1 at its peak.
+    strength: f64,
+}
+
+fn smooth(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Piecewise-linear interpolation through points sorted by `x`, holding the end values outside them.
+fn interp(points: impl Iterator<Item = (f64, f64)>, x: f64) -> Option<f64> {
+    let mut prev: Option<(f64, f64)> = None;
+    for (px, py) in points {
+        if x <= px {
+            return Some(match prev {
+                Some((qx, qy)) if px > qx => qy + (py - qy) * (x - qx) / (px - qx),
+                _ => py,
+            });
+        }
+        prev = Some((px, py));
+    }
+    prev.map(|(_, py)| py)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic_weather() {
+        let cfg: WindCfg = toml::from_str(
+            r#"
+            seed = 42
+            zonal = [
+                { latitude = -30, speed = -20 },
+                { latitude = 0, speed = 40 },
+                { latitude = 30, speed = -20 },
+            ]
+            zonal_profile = [{ height = 0, factor = 0.5 }, { height = "10 km", factor = 1.0 }]
+            turbulence = { intensity = 5.0, length_scale = "500 m", time_scale = 20.0 }
+            storms = { count = 4, radius = "300 km", height = "12 km", max_wind = 60.0, pressure_drop = 0.3, temperature_drop = 20.0, lifetime = 86400.0 }
+            "#,
+        )
+        .unwrap();
+        let calm = WindCfg {
+            turbulence: None,
+            storms: None,
+            ..cfg.clone()
+        };
+
+        // eastward jet at the equator, full strength aloft
+        let equator = DVec3::new(1.7e7, 0.0, 0.0);
+        let zonal =
+            WindField::new(&calm).apply(equator, 10_000.0, 0.0, &mut AtmosphereSample::default());
+        assert!((zonal - DVec3::new(0.0, 40.0, 0.0)).length() < 1e-9);
+
+        // the same seed gives the same gusts; another seed does not
+        let sample = |cfg: &WindCfg, time: f64| {
+            WindField::new(cfg).apply(equator, 5_000.0, time, &mut AtmosphereSample::default())
+        };
+        assert_eq!(sample(&cfg, 1234.5), sample(&cfg, 1234.5));
+        assert_ne!(sample(&cfg, 1234.5), sample(&cfg, 1300.0));
+        let reseeded = WindCfg {
+            seed: 7,
+            ..cfg.clone()
+        };
+        assert_ne!(sample(&cfg, 1234.5), sample(&reseeded, 1234.5));
+
+        // pressure drops in the eye of a storm
+        let field = WindField::new(&cfg);
+        let storms = cfg.storms.unwrap();
+        let time = 0.4 * storms.lifetime;
+        let eye = field.storm_cells(&storms, time).next().unwrap();
+        let mut sample = AtmosphereSample {
+            pressure: 1e5,
+            density: 1.2,
+            temperature: 285.0,
+            ..Default::default()
+        };
+        let wind = field.storms(
+            &storms,
+            eye.center * 1.7e7,
+            eye.center,
+            0.0,
+            time,
+            &mut sample,
+        );
+        assert!(sample.pressure < 1e5 * (1.0 - 0.2 * eye.strength));
+        assert!(sample.temperature < 285.0);
+        assert!(wind.length() < 1e-6);
+    }
+}
@@ -0,0 +1,597 @@
+use bevy::{
+    math::{DMat3, DQuat, DVec3},
+    prelude::*,
+};
+
+use crate::{
+    GameState,
+    orrery::{Celestial, Orrery},
+    physics::{
+        AngularVelocity, MassProps, SimTime, Velocity, apply_forces, docking::DockChild,
+        spatial::SpatialIndex,
+    },
+    precision::{PreciseTransform, ToMetersExt, ToMillimetersExt},
+    vessel::Part,
+};
+
+pub(super) fn run_collision(app: &mut App) {
+    app.add_event::<CrashEvent>().add_systems(
+        FixedUpdate,
+        (vessel_collisions, surface_collisions, report_crashes)
+            .chain()
+            .after(apply_forces)
+            .run_if(in_state(GameState::Game)),
+    );
+}
+
+/// The collision geometry of a rigid body: a set of oriented boxes in the body's local frame.
+#[derive(Component, Default, Clone)]
+pub struct Collider {
+    pub boxes: Vec<ColliderBox>,
+    /// Radius (m) of a sphere around the body's origin that encloses every box.
+    pub bounding_radius: f64,
+}
+
+impl Collider {
+    pub fn new(boxes: Vec<ColliderBox>) -> Self {
+        let bounding_radius = boxes
+            .iter()
+            .map(|b| b.center.length() + b.half_extents.length())
+            .fold(0.0, f64::max);
+        Self {
+            boxes,
+            bounding_radius,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ColliderBox {
+    /// The part this box belongs to.
+    pub part: Entity,
+    /// Centre of the box (m), relative to the body's centre of mass.
+    pub center: DVec3,
+    pub rotation: DQuat,
+    /// Half of the box's size (m) along each of its local axes.
+    pub half_extents: DVec3,
+    /// Impact speed (m/s) along the contact normal beyond which the part crashes.
+    pub crash_tolerance: f64,
+    pub restitution: f64,
+    pub friction: f64,
+}
+
+/// Sent whenever a part hits something faster than it can tolerate.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CrashEvent {
+    pub vessel: Entity,
+    pub part: Entity,
+    /// Closing speed (m/s) along the contact normal.
+    pub impact_speed: f64,
+}
+
+/// The components of a rigid body that takes part in collisions.
+type CollidingBody = (
+    Entity,
+    &'static Collider,
+    &'static MassProps,
+    &'static mut PreciseTransform,
+    &'static mut Velocity,
+    &'static mut AngularVelocity,
+);
+
+/// Extra reach (m) of broad-phase queries, covering relative motion since the spatial index was built.
+const MOTION_SLACK_M: f64 = 10.0;
+
+/// Detects and resolves collisions between pairs of rigid bodies.
+fn vessel_collisions(
+    index: Res<SpatialIndex>,
+    mut bodies: Query<CollidingBody, Without<DockChild>>,
+    mut crashes: EventWriter<CrashEvent>,
+) {
+    // broad phase: bounding spheres around the indexed positions
+    let max_radius = bodies
+        .iter()
+        .map(|(_, collider, ..)| collider.bounding_radius)
+        .fold(0.0, f64::max);
+    let mut pairs = vec![];
+    for (ent_a, posn_a) in index.iter() {
+        let Ok((_, collider_a, _, ptf_a, ..)) = bodies.get(ent_a) else {
+            continue;
+        };
+        let reach_m = collider_a.bounding_radius + max_radius + MOTION_SLACK_M;
+        for neighbour in index.within_radius(posn_a, reach_m * 1000.0) {
+            if neighbour.entity <= ent_a {
+                continue;
+            }
+            let Ok((ent_b, collider_b, _, ptf_b, ..)) = bodies.get(neighbour.entity) else {
+                continue;
+            };
+            // re-check against the current positions
+            let dist_m = (ptf_b.translation_mm - ptf_a.translation_mm)
+                .to_meters_64()
+                .length();
+            if dist_m < collider_a.bounding_radius + collider_b.bounding_radius {
+                pairs.push((ent_a, ent_b));
+            }
+        }
+    }
+
+    // narrow phase and response
+    for (ent_a, ent_b) in pairs {
+        let Ok([a, b]) = bodies.get_many_mut([ent_a, ent_b]) else {
+            continue;
+        };
+        let (_, col_a, mass_a, mut ptf_a, mut vel_a, mut angvel_a) = a;
+        let (_, col_b, mass_b, mut ptf_b, mut vel_b, mut angvel_b) = b;
+
+        // everything is computed relative to A, to keep precision
+        let offset_b = (ptf_b.translation_mm - ptf_a.translation_mm).to_meters_64();
+
+        let mut deepest: Option<(Contact, &ColliderBox, &ColliderBox)> = None;
+        for box_a in &col_a.boxes {
+            let obb_a = Obb::new(box_a, DVec3::ZERO, ptf_a.rotation);
+            for box_b in &col_b.boxes {
+                let obb_b = Obb::new(box_b, offset_b, ptf_b.rotation);
+                if let Some(contact) = obb_a.contact(&obb_b)
+                    && deepest.is_none_or(|(d, _, _)| contact.depth > d.depth)
+                {
+                    deepest = Some((contact, box_a, box_b));
+                }
+            }
+        }
+        let Some((contact, box_a, box_b)) = deepest else {
+            continue;
+        };
+
+        let mut state_a = BodyState::new(mass_a, ptf_a.rotation, vel_a.0, angvel_a.0);
+        let mut state_b = BodyState::new(mass_b, ptf_b.rotation, vel_b.0, angvel_b.0);
+        let impact_speed = contact_impulse(
+            &mut state_a,
+            &mut state_b,
+            contact.point,
+            contact.point - offset_b,
+            contact.normal,
+            box_a.restitution.min(box_b.restitution),
+            (box_a.friction * box_b.friction).sqrt(),
+        );
+        vel_a.0 = state_a.velocity;
+        angvel_a.0 = state_a.ang_vel;
+        vel_b.0 = state_b.velocity;
+        angvel_b.0 = state_b.ang_vel;
+
+        // push the bodies apart, in proportion to their inverse masses
+        let inv_mass_sum = state_a.inv_mass + state_b.inv_mass;
+        if inv_mass_sum > 0.0 {
+            let separation = contact.normal * contact.depth / inv_mass_sum;
+            ptf_a.translation_mm -= (separation * state_a.inv_mass).to_millimeters();
+            ptf_b.translation_mm += (separation * state_b.inv_mass).to_millimeters();
+        }
+
+        for (vessel, part_box) in [(ent_a, box_a), (ent_b, box_b)] {
+            if impact_speed > part_box.crash_tolerance {
+                crashes.write(CrashEvent {
+                    vessel,
+                    part: part_box.part,
+                    impact_speed,
+                });
+            }
+        }
+    }
+}
+
+/// Detects and resolves contacts between rigid bodies and the surfaces of celestial bodies.
+fn surface_collisions(
+    orrery: Res<Orrery>,
+    time: SimTime,
+    celestials: Query<(&Celestial, &PreciseTransform)>,
+    mut bodies: Query<CollidingBody, (Without<DockChild>, Without<Celestial>)>,
+    mut crashes: EventWriter<CrashEvent>,
+) {
+    let epoch = time.epoch();
+    for (vessel, collider, mass, mut ptf, mut vel, mut angvel) in bodies.iter_mut() {
+        for (celestial, cel_ptf) in celestials.iter() {
+            let Some(body) = orrery.get_body(&celestial.0) else {
+                continue;
+            };
+            let rel = (ptf.translation_mm - cel_ptf.translation_mm).to_meters_64();
+            if rel.length() - collider.bounding_radius > body.radius {
+                continue;
+            }
+
+            // the deepest box corner below the surface
+            let mut deepest: Option<(DVec3, f64, &ColliderBox)> = None;
+            for part_box in &collider.boxes {
+                for corner in Obb::new(part_box, DVec3::ZERO, ptf.rotation).corners() {
+                    let depth = body.radius - (rel + corner).length();
+                    if depth > 0.0 && deepest.is_none_or(|(_, d, _)| depth > d) {
+                        deepest = Some((corner, depth, part_box));
+                    }
+                }
+            }
+            let Some((corner, depth, part_box)) = deepest else {
+                continue;
+            };
+
+            let normal = (rel + corner).normalize();
+            let surface_velocity = orrery
+                .solve_surface_velocity(&celestial.0, rel + corner, epoch)
+                .unwrap_or_default();
+            // celestial bodies have effectively infinite mass
+            let mut state_cel = BodyState {
+                inv_mass: 0.0,
+                inv_inertia: DMat3::ZERO,
+                velocity: surface_velocity,
+                ang_vel: DVec3::ZERO,
+            };
+            let mut state = BodyState::new(mass, ptf.rotation, vel.0, angvel.0);
+            let impact_speed = contact_impulse(
+                &mut state_cel,
+                &mut state,
+                DVec3::ZERO,
+                corner,
+                normal,
+                part_box.restitution,
+                part_box.friction,
+            );
+            vel.0 = state.velocity;
+            angvel.0 = state.ang_vel;
+            ptf.translation_mm += (normal * depth).to_millimeters();
+
+            if impact_speed > part_box.crash_tolerance {
+                crashes.write(CrashEvent {
+                    vessel,
+                    part: part_box.part,
+                    impact_speed,
+                });
+            }
+        }
+    }
+}
+
+fn report_crashes(mut crashes: EventReader<CrashEvent>, parts: Query<&Part>) {
+    for crash in crashes.read() {
+        let Ok(part) = parts.get(crash.part) else {
+            continue;
+        };
+        warn!(
+            vessel = ?crash.vessel,
+            part = display(&part.id),
+            proto = display(&part.proto),
+            impact_speed = display(crash.impact_speed),
+            "part crashed"
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Contact {
+    /// Contact normal, pointing from the first body towards the second.
+    normal: DVec3,
+    /// Penetration depth (m).
+    depth: f64,
+    /// Contact point (m), in the frame the boxes were built in.
+    point: DVec3,
+}
+
+/// An oriented bounding box in world orientation, positioned relative to some reference point.
+struct Obb {
+    center: DVec3,
+    axes: [DVec3; 3],
+    half_extents: DVec3,
+}
+
+impl Obb {
+    fn new(part_box: &ColliderBox, body_offset: DVec3, body_rotation: DQuat) -> Self {
+        let rotation = body_rotation * part_box.rotation;
+        Self {
+            center: body_offset + body_rotation * part_box.center,
+            axes: [
+                rotation * DVec3::X,
+                rotation * DVec3::Y,
+                rotation * DVec3::Z,
+            ],
+            half_extents: part_box.half_extents,
+        }
+    }
+
+    /// Half of the box's extent when projected onto `axis`.
+    fn projected_radius(&self, axis: DVec3) -> f64 {
+        (0..3)
+            .map(|i| self.half_extents[i] * self.axes[i].dot(axis).abs())
+            .sum()
+    }
+
+    /// The point of the box furthest along `dir`. Faces and edges perpendicular to `dir` yield their centre.
+    fn support(&self, dir: DVec3) -> DVec3 {
+        let mut point = self.center;
+        for i in 0..3 {
+            let d = self.axes[i].dot(dir);
+            if d.abs() > 1e-6 {
+                point += self.axes[i] * self.half_extents[i] * d.signum();
+            }
+        }
+        point
+    }
+
+    fn corners(&self) -> impl Iterator<Item = DVec3> + '_ {
+        (0..8).map(|i| {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            self.center
+                + self.axes[0] * self.half_extents.x * sign(1)
+                + self.axes[1] * self.half_extents.y * sign(2)
+                + self.axes[2] * self.half_extents.z * sign(4)
+        })
+    }
+
+    /// Separating-axis test between two boxes, yielding the contact of least penetration.
+    fn contact(&self, other: &Obb) -> Option<Contact> {
+        let d = other.center - self.center;
+        let mut candidates = Vec::with_capacity(15);
+        candidates.extend(self.axes);
+        candidates.extend(other.axes);
+        for a in self.axes {
+            for b in other.axes {
+                candidates.push(a.cross(b));
+            }
+        }
+
+        let mut depth = f64::MAX;
+        let mut normal = DVec3::ZERO;
+        for axis in candidates {
+            // parallel edges give degenerate cross products, which are already covered by the face axes
+            let Some(axis) = axis.try_normalize() else {
+                continue;
+            };
+            let dist = d.dot(axis);
+            let overlap = self.projected_radius(axis) + other.projected_radius(axis) - dist.abs();
+            if overlap < 0.0 {
+                return None;
+            }
+            if overlap < depth {
+                depth = overlap;
+                normal = if dist < 0.0 { -axis } else { axis };
+            }
+        }
+
+        Some(Contact {
+            normal,
+            depth,
+            point: (self.support(normal) + other.support(-normal)) * 0.5,
+        })
+    }
+}
+
+/// The dynamic state of a body taking part in a contact.
+struct BodyState {
+    inv_mass: f64,
+    /// Inverse inertia tensor, in the world frame.
+    inv_inertia: DMat3,
+    velocity: DVec3,
+    ang_vel: DVec3,
+}
+
+impl BodyState {
+    fn new(mass: &MassProps, rotation: DQuat, velocity: DVec3, ang_vel: DVec3) -> Self {
+        let rot = DMat3::from_quat(rotation);
+        Self {
+            inv_mass: 1.0 / mass.mass,
+            inv_inertia: rot * mass.inertia_inv * rot.transpose(),
+            velocity,
+            ang_vel,
+        }
+    }
+
+    fn point_velocity(&self, r: DVec3) -> DVec3 {
+        self.velocity + self.ang_vel.cross(r)
+    }
+
+    /// The inverse of the effective mass felt by an impulse along `dir` applied at `r`.
+    fn inv_effective_mass(&self, r: DVec3, dir: DVec3) -> f64 {
+        self.inv_mass + dir.dot((self.inv_inertia * r.cross(dir)).cross(r))
+    }
+
+    fn apply_impulse(&mut self, r: DVec3, impulse: DVec3) {
+        self.velocity += impulse * self.inv_mass;
+        self.ang_vel += self.inv_inertia * r.cross(impulse);
+    }
+}
+
+/// Applies a contact impulse with restitution and Coulomb friction between two bodies.
+/// `r_a` and `r_b` are the offsets of the contact point from each body's centre of mass, and `normal` points from A to B.
+/// Returns the closing speed along the normal, or zero if the bodies are already separating.
+fn contact_impulse(
+    a: &mut BodyState,
+    b: &mut BodyState,
+    r_a: DVec3,
+    r_b: DVec3,
+    normal: DVec3,
+    restitution: f64,
+    friction: f64,
+) -> f64 {
+    let v_rel = b.point_velocity(r_b) - a.point_velocity(r_a);
+    let v_normal = v_rel.dot(normal);
+    if v_normal >= 0.0 {
+        return 0.0;
+    }
+
+    // normal impulse
+    let k_normal = a.inv_effective_mass(r_a, normal) + b.inv_effective_mass(r_b, normal);
+    let j_normal = -(1.0 + restitution) * v_normal / k_normal;
+    a.apply_impulse(r_a, -normal * j_normal);
+    b.apply_impulse(r_b, normal * j_normal);
+
+    // friction impulse, bounded by the Coulomb cone
+    let v_rel = b.point_velocity(r_b) - a.point_velocity(r_a);
+    let v_tangent = v_rel - normal * v_rel.dot(normal);
+    if let Some(tangent) = v_tangent.try_normalize() {
+        let k_tangent = a.inv_effective_mass(r_a, tangent) + b.inv_effective_mass(r_b, tangent);
+        let j_tangent = (-v_rel.dot(tangent) / k_tangent).max(-friction * j_normal);
+        a.apply_impulse(r_a, -tangent * j_tangent);
+        b.apply_impulse(r_b, tangent * j_tangent);
+    }
+
+    -v_normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(part: Entity, crash_tolerance: f64) -> ColliderBox {
+        ColliderBox {
+            part,
+            center: DVec3::ZERO,
+            rotation: DQuat::IDENTITY,
+            half_extents: DVec3::ONE,
+            crash_tolerance,
+            restitution: 0.0,
+            friction: 0.5,
+        }
+    }
+
+    fn point_mass(velocity: DVec3) -> BodyState {
+        BodyState {
+            inv_mass: 1.0,
+            inv_inertia: DMat3::ZERO,
+            velocity,
+            ang_vel: DVec3::ZERO,
+        }
+    }
+
+    #[test]
+    fn box_contacts() {
+        let part = unit_box(Entity::PLACEHOLDER, 1.0);
+        let a = Obb::new(&part, DVec3::ZERO, DQuat::IDENTITY);
+
+        let b = Obb::new(&part, DVec3::new(1.8, 0.5, 0.0), DQuat::IDENTITY);
+        let contact = a.contact(&b).unwrap();
+        assert!(contact.normal.abs_diff_eq(DVec3::X, 1e-12));
+        assert!((contact.depth - 0.2).abs() < 1e-12);
+        assert!((contact.point.x - 0.9).abs() < 1e-12);
+        // pointing from the first box towards the second
+        assert!(
+            b.contact(&a)
+                .unwrap()
+                .normal
+                .abs_diff_eq(DVec3::NEG_X, 1e-12)
+        );
+
+        // a corner of a box turned 45° about Z digs into the face of the other
+        let turned = Obb::new(
+            &part,
+            DVec3::new(1.6, 0.0, 0.0),
+            DQuat::from_rotation_z(0.25 * std::f64::consts::PI),
+        );
+        let contact = a.contact(&turned).unwrap();
+        assert!(contact.normal.abs_diff_eq(DVec3::X, 1e-12));
+        assert!((contact.depth - (std::f64::consts::SQRT_2 - 0.6)).abs() < 1e-12);
+
+        let apart = Obb::new(&part, DVec3::new(2.01, 0.0, 0.0), DQuat::IDENTITY);
+        assert!(a.contact(&apart).is_none());
+    }
+
+    #[test]
+    fn contact_impulses() {
+        // equal masses meeting head on swap velocities when perfectly elastic...
+        let (mut a, mut b) = (point_mass(DVec3::X), point_mass(DVec3::NEG_X));
+        let speed = contact_impulse(&mut a, &mut b, DVec3::ZERO, DVec3::ZERO, DVec3::X, 1.0, 0.0);
+        assert_eq!(speed, 2.0);
+        assert!(a.velocity.abs_diff_eq(DVec3::NEG_X, 1e-12));
+        assert!(b.velocity.abs_diff_eq(DVec3::X, 1e-12));
+
+        // ...and move on together when perfectly inelastic
+        let (mut a, mut b) = (point_mass(DVec3::X * 3.0), point_mass(DVec3::NEG_X));
+        contact_impulse(&mut a, &mut b, DVec3::ZERO, DVec3::ZERO, DVec3::X, 0.0, 0.0);
+        assert!(a.velocity.abs_diff_eq(DVec3::X, 1e-12));
+        assert!(b.velocity.abs_diff_eq(DVec3::X, 1e-12));
+
+        // sliding onto immovable ground: friction is bounded by the Coulomb cone...
+        let ground = || BodyState {
+            inv_mass: 0.0,
+            ..point_mass(DVec3::ZERO)
+        };
+        let (mut g, mut body) = (ground(), point_mass(DVec3::new(3.0, -1.0, 0.0)));
+        let speed = contact_impulse(
+            &mut g,
+            &mut body,
+            DVec3::ZERO,
+            DVec3::ZERO,
+            DVec3::Y,
+            0.0,
+            0.5,
+        );
+        assert_eq!(speed, 1.0);
+        assert!(body.velocity.abs_diff_eq(DVec3::new(2.5, 0.0, 0.0), 1e-12));
+        assert_eq!(g.velocity, DVec3::ZERO);
+
+        // ...and never reverses the sliding
+        let (mut g, mut body) = (ground(), point_mass(DVec3::new(3.0, -1.0, 0.0)));
+        contact_impulse(
+            &mut g,
+            &mut body,
+            DVec3::ZERO,
+            DVec3::ZERO,
+            DVec3::Y,
+            0.0,
+            10.0,
+        );
+        assert!(body.velocity.abs_diff_eq(DVec3::ZERO, 1e-12));
+
+        // separating bodies are left alone
+        let (mut g, mut body) = (ground(), point_mass(DVec3::new(3.0, 1.0, 0.0)));
+        let speed = contact_impulse(
+            &mut g,
+            &mut body,
+            DVec3::ZERO,
+            DVec3::ZERO,
+            DVec3::Y,
+            0.0,
+            0.5,
+        );
+        assert_eq!(speed, 0.0);
+        assert_eq!(body.velocity, DVec3::new(3.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn crashes_beyond_tolerance() {
+        let mut app = App::new();
+        app.add_event::<CrashEvent>()
+            .add_systems(Update, vessel_collisions);
+        let world = app.world_mut();
+        let (fragile, sturdy) = (world.spawn_empty().id(), world.spawn_empty().id());
+        let mut body = |x: i64, velocity: f64, part_box: ColliderBox| {
+            world
+                .spawn((
+                    Collider::new(vec![part_box]),
+                    MassProps::default(),
+                    PreciseTransform {
+                        translation_mm: bevy::math::I64Vec3::new(x, 0, 0),
+                        rotation: DQuat::IDENTITY,
+                    },
+                    Velocity(DVec3::X * velocity),
+                    AngularVelocity::default(),
+                ))
+                .id()
+        };
+        let a = body(0, 5.0, unit_box(fragile, 8.0));
+        let b = body(1_900, -5.0, unit_box(sturdy, 12.0));
+        world.insert_resource(SpatialIndex::new(vec![
+            (a, bevy::math::I64Vec3::ZERO),
+            (b, bevy::math::I64Vec3::new(1_900, 0, 0)),
+        ]));
+        app.update();
+
+        let events = app.world().resource::<Events<CrashEvent>>();
+        let crashes = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(crashes.len(), 1);
+        assert_eq!(crashes[0].vessel, a);
+        assert_eq!(crashes[0].part, fragile);
+        assert!((crashes[0].impact_speed - 10.0).abs() < 1e-12);
+        // pushed apart, and no longer closing
+        let world = app.world();
+        let gap = world.get::<PreciseTransform>(b).unwrap().translation_mm.x
+            - world.get::<PreciseTransform>(a).unwrap().translation_mm.x;
+        assert_eq!(gap, 2_000);
+        assert!(world.get::<Velocity>(a).unwrap().0.x <= world.get::<Velocity>(b).unwrap().0.x);
+    }
+}
@@ -0,0 +1,325 @@
+use bevy::{
+    math::{DMat3, DQuat, DVec3, EulerRot},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    orrery::{Celestial, Orrery},
+    physics::{SimTime, Velocity, WithinSoi},
+    precision::{PreciseTransform, ToMetersExt, ToMillimetersExt},
+};
+
+pub(super) fn run_geodesy(app: &mut App) {
+    app.add_systems(
+        FixedPostUpdate,
+        update_surface_state.run_if(in_state(GameState::Game)),
+    );
+}
+
+/// A position over a spherical body, in its body-fixed frame: Z along the spin axis and X through the prime
+/// meridian. Latitude and longitude are in radians, and the altitude is above the body's radius, in meters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Geodetic {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+impl Geodetic {
+    /// The geodetic position of a point (m) in the body-fixed frame of a body of the given radius (m).
+    pub fn from_body_fixed(position: DVec3, radius: f64) -> Self {
+        let r = position.length();
+        if r == 0.0 {
+            return Self {
+                altitude: -radius,
+                ..default()
+            };
+        }
+        Self {
+            latitude: (position.z / r).clamp(-1.0, 1.0).asin(),
+            longitude: position.y.atan2(position.x),
+            altitude: r - radius,
+        }
+    }
+
+    /// The point (m) in the body-fixed frame of a body of the given radius (m).
+    pub fn to_body_fixed(self, radius: f64) -> DVec3 {
+        self.up() * (radius + self.altitude)
+    }
+
+    /// Local vertical, in the body-fixed frame.
+    pub fn up(&self) -> DVec3 {
+        let (sin_lat, cos_lat) = self.latitude.sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.sin_cos();
+        DVec3::new(cos_lat * cos_lon, cos_lat * sin_lon, sin_lat)
+    }
+
+    /// Columns east, north and up of the local tangent frame, in the body-fixed frame. At the poles, north is taken
+    /// along the prime meridian.
+    pub fn enu(&self) -> DMat3 {
+        let (sin_lat, cos_lat) = self.latitude.sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.sin_cos();
+        let east = DVec3::new(-sin_lon, cos_lon, 0.0);
+        let north = DVec3::new(-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat);
+        DMat3::from_cols(east, north, self.up())
+    }
+
+    /// Columns north, east and down of the local tangent frame, in the body-fixed frame.
+    pub fn ned(&self) -> DMat3 {
+        let enu = self.enu();
+        DMat3::from_cols(enu.y_axis, enu.x_axis, -enu.z_axis)
+    }
+}
+
+/// Orientation of a vessel relative to the local horizon, in radians. The vessel's nose is its -Z axis, its right
+/// side +X and its top +Y; the angles are those of aircraft: heading clockwise from north, pitch up from the
+/// horizon, and roll right wing down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Attitude {
+    pub heading: f64,
+    #[serde(default)]
+    pub pitch: f64,
+    #[serde(default)]
+    pub roll: f64,
+}
+
+/// Maps the vessel's axes onto the aircraft axes: forward, right wing and down.
+const VESSEL_TO_AIRCRAFT: DMat3 = DMat3::from_cols(DVec3::Y, DVec3::NEG_Z, DVec3::NEG_X);
+
+impl Attitude {
+    /// The attitude of a vessel whose rotation, in the body-fixed frame, is `rotation`, over a geodetic position.
+    pub fn from_rotation(position: &Geodetic, rotation: DQuat) -> Self {
+        let ned = DQuat::from_mat3(&position.ned());
+        let aircraft = ned.inverse() * rotation * DQuat::from_mat3(&VESSEL_TO_AIRCRAFT).inverse();
+        let (heading, pitch, roll) = aircraft.to_euler(EulerRot::ZYX);
+        Self {
+            heading: heading.rem_euclid(std::f64::consts::TAU),
+            pitch,
+            roll,
+        }
+    }
+
+    /// The rotation of a vessel in the body-fixed frame, at this attitude over a geodetic position.
+    pub fn to_rotation(self, position: &Geodetic) -> DQuat {
+        let ned = DQuat::from_mat3(&position.ned());
+        let aircraft = DQuat::from_euler(EulerRot::ZYX, self.heading, self.pitch, self.roll);
+        ned * aircraft * DQuat::from_mat3(&VESSEL_TO_AIRCRAFT)
+    }
+}
+
+/// Where and how an object is moving over the body whose sphere of influence it's in. Updated at the end of every
+/// tick.
+#[derive(Component, Default, Debug)]
+pub struct SurfaceState {
+    pub position: Geodetic,
+    pub attitude: Attitude,
+    /// Velocity (m/s) relative to the rotating surface, in the local north–east–down frame.
+    pub velocity_ned: DVec3,
+}
+
+impl SurfaceState {
+    /// Horizontal speed (m/s) over the ground.
+    pub fn ground_speed(&self) -> f64 {
+        self.velocity_ned.truncate().length()
+    }
+
+    /// Speed (m/s) away from the body's centre.
+    pub fn vertical_speed(&self) -> f64 {
+        -self.velocity_ned.z
+    }
+
+    /// Angle (rad) of the surface-relative velocity above the horizon.
+    pub fn flight_path_angle(&self) -> f64 {
+        self.vertical_speed().atan2(self.ground_speed())
+    }
+
+    /// Direction of the horizontal motion (rad), clockwise from north.
+    pub fn track(&self) -> f64 {
+        self.velocity_ned
+            .y
+            .atan2(self.velocity_ned.x)
+            .rem_euclid(std::f64::consts::TAU)
+    }
+}
+
+/// The precise transform of a vessel at a geodetic position and attitude over a body, and its inertial velocity (m/s)
+/// given its velocity relative to the body's surface, in the local north–east–down frame. Returns None if the body is
+/// not found.
+pub fn surface_transform(
+    orrery: &Orrery,
+    body: &str,
+    position: &Geodetic,
+    attitude: &Attitude,
+    velocity_ned: DVec3,
+    epoch: hifitime::Epoch,
+) -> Option<(PreciseTransform, DVec3)> {
+    let cfg = orrery.get_body(body)?;
+    let rotation = orrery.solve_rotation(body, epoch)?;
+    let offset = rotation * position.to_body_fixed(cfg.radius);
+    let transform = PreciseTransform {
+        translation_mm: orrery.solve_position(body, epoch)? + offset.to_millimeters(),
+        rotation: rotation * attitude.to_rotation(position),
+    };
+    let velocity = orrery.solve_surface_velocity(body, offset, epoch)?
+        + rotation * (position.ned() * velocity_ned);
+    Some((transform, velocity))
+}
+
+fn update_surface_state(
+    orrery: Res<Orrery>,
+    time: SimTime,
+    objects: Query<(&PreciseTransform, &Velocity, &WithinSoi, &mut SurfaceState)>,
+    planets: Query<(&Celestial, &PreciseTransform)>,
+) {
+    let epoch = time.epoch();
+    for (ptf, velocity, soi, mut state) in objects {
+        let Ok((planet, planet_ptf)) = planets.get(soi.0) else {
+            continue;
+        };
+        let (Some(body), Some(rotation)) = (
+            orrery.get_body(&planet.0),
+            orrery.solve_rotation(&planet.0, epoch),
+        ) else {
+            continue;
+        };
+        let offset = (ptf.translation_mm - planet_ptf.translation_mm).to_meters_64();
+        let Some(surface_velocity) = orrery.solve_surface_velocity(&planet.0, offset, epoch) else {
+            continue;
+        };
+        let to_body = rotation.inverse();
+        let position = Geodetic::from_body_fixed(to_body * offset, body.radius);
+        *state = SurfaceState {
+            position,
+            attitude: Attitude::from_rotation(&position, to_body * ptf.rotation),
+            velocity_ned: position.ned().transpose() * (to_body * (velocity.0 - surface_velocity)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hifitime::Epoch;
+
+    use super::*;
+
+    #[test]
+    fn geodetic_round_trip() {
+        let radius = 6.4e6;
+        for position in [
+            DVec3::new(7.0e6, 0.0, 0.0),
+            DVec3::new(-1.0e6, 4.0e6, 5.0e6),
+            DVec3::new(2.0e6, -3.0e6, -6.0e6),
+        ] {
+            let geo = Geodetic::from_body_fixed(position, radius);
+            assert!((geo.to_body_fixed(radius) - position).length() < 1e-6);
+            let enu = geo.enu();
+            assert!((enu * enu.transpose() - DMat3::IDENTITY).abs_diff_eq(DMat3::ZERO, 1e-12));
+            assert!(enu.x_axis.cross(enu.y_axis).abs_diff_eq(enu.z_axis, 1e-12));
+        }
+    }
+
+    #[test]
+    fn attitude_round_trip() {
+        let position = Geodetic {
+            latitude: 0.7,
+            longitude: -2.0,
+            altitude: 1000.0,
+        };
+        let ned = position.ned();
+        // level, facing north: the nose points north and the top up
+        let level = Attitude::default().to_rotation(&position);
+        assert!((level * DVec3::NEG_Z).abs_diff_eq(ned.x_axis, 1e-12));
+        assert!((level * DVec3::Y).abs_diff_eq(-ned.z_axis, 1e-12));
+        // facing east, nose up
+        let climbing = Attitude {
+            heading: std::f64::consts::FRAC_PI_2,
+            pitch: 0.3,
+            roll: 0.0,
+        }
+        .to_rotation(&position);
+        let nose = climbing * DVec3::NEG_Z;
+        assert!(nose.dot(ned.y_axis) > 0.9 && nose.dot(-ned.z_axis) > 0.29);
+
+        for attitude in [
+            Attitude {
+                heading: 0.1,
+                pitch: 0.2,
+                roll: 0.3,
+            },
+            Attitude {
+                heading: 4.0,
+                pitch: -1.2,
+                roll: -2.5,
+            },
+        ] {
+            let back = Attitude::from_rotation(&position, attitude.to_rotation(&position));
+            assert!((back.heading - attitude.heading).abs() < 1e-9, "{back:?}");
+            assert!((back.pitch - attitude.pitch).abs() < 1e-9, "{back:?}");
+            assert!((back.roll - attitude.roll).abs() < 1e-9, "{back:?}");
+        }
+    }
+
+    #[test]
+    fn surface_transform_round_trip() {
+        let yaml = r#"
+name: "planet"
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mass: "1 massSol"
+  - name: "Planet"
+    class: planet
+    parent: "Star"
+    mass: "1 massEarth"
+    radius: 6.4e6
+    semi_major: "1 au"
+    rotation_period: "24 h"
+    obliquity: 23.0
+"#;
+        let orrery = Orrery::init(
+            serde_yml::from_str(yaml).unwrap(),
+            Epoch::from_tai_seconds(0.0),
+        )
+        .unwrap();
+        let epoch = Epoch::from_tai_seconds(5000.0);
+        let position = Geodetic {
+            latitude: 0.5,
+            longitude: 1.0,
+            altitude: 2000.0,
+        };
+        let attitude = Attitude {
+            heading: 1.0,
+            pitch: 0.1,
+            roll: -0.2,
+        };
+        let velocity_ned = DVec3::new(10.0, -5.0, 2.0);
+        let (transform, velocity) =
+            surface_transform(&orrery, "Planet", &position, &attitude, velocity_ned, epoch)
+                .unwrap();
+
+        // placed where asked, in the rotating body-fixed frame
+        let rotation = orrery.solve_rotation("Planet", epoch).unwrap();
+        let offset = (transform.translation_mm - orrery.solve_position("Planet", epoch).unwrap())
+            .to_meters_64();
+        let back = Geodetic::from_body_fixed(rotation.inverse() * offset, 6.4e6);
+        assert!((back.latitude - position.latitude).abs() < 1e-9, "{back:?}");
+        assert!(
+            (back.longitude - position.longitude).abs() < 1e-9,
+            "{back:?}"
+        );
+        assert!((back.altitude - position.altitude).abs() < 1e-3, "{back:?}");
+        let back = Attitude::from_rotation(&position, rotation.inverse() * transform.rotation);
+        assert!((back.heading - attitude.heading).abs() < 1e-9, "{back:?}");
+        assert!((back.pitch - attitude.pitch).abs() < 1e-9, "{back:?}");
+        assert!((back.roll - attitude.roll).abs() < 1e-9, "{back:?}");
+
+        // moving with the planet along its orbit and with its spin, on top of the velocity over the ground
+        let spin = rotation * DVec3::Z * (std::f64::consts::TAU / 86_400.0);
+        let surface = orrery.solve_absolute_velocity("Planet", epoch).unwrap() + spin.cross(offset);
+        let relative = rotation.inverse() * (velocity - surface);
+        assert!((position.ned().transpose() * relative).abs_diff_eq(velocity_ned, 1e-6));
+    }
+}
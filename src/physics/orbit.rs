@@ -0,0 +1,130 @@
+use bevy::{math::DVec3, prelude::*};
+
+use crate::{
+    GameState,
+    camera::CameraFocus,
+    orrery::{
+        Celestial, Orrery,
+        kepler::{self, Conic},
+    },
+    physics::{SimTime, Velocity, WithinSoi},
+    precision::{FloatingOrigin, PreciseTransform, ToMetersExt, ToMillimetersExt},
+};
+
+/// Points drawn along a predicted orbit.
+const PATH_POINTS: usize = 128;
+/// How far (s) ahead an open orbit is drawn.
+const OPEN_PATH_SPAN: f64 = 86_400.0;
+
+pub(super) fn run_orbit(app: &mut App) {
+    app.add_systems(
+        FixedPostUpdate,
+        predict_orbits.run_if(in_state(GameState::Game)),
+    )
+    .add_systems(
+        Update,
+        draw_predicted_orbit.run_if(in_state(GameState::Game)),
+    );
+}
+
+/// The conic an object would follow around the body whose sphere of influence it's in, were no other force to act
+/// on it. Updated at the end of every tick.
+#[derive(Component, Default, Debug)]
+pub struct PredictedOrbit {
+    /// Gravitational parameter (m³/s²) of the body.
+    pub mu: f64,
+    /// Radius (m) of the body.
+    pub radius: f64,
+    /// Position (m) relative to the body.
+    pub position: DVec3,
+    /// Velocity (m/s) relative to the body, in the inertial frame.
+    pub velocity: DVec3,
+    pub conic: Conic,
+    /// Time (s) until the next periapsis, or None if an open orbit is already past it.
+    pub time_to_periapsis: Option<f64>,
+}
+
+impl PredictedOrbit {
+    /// Position (m) and velocity (m/s) relative to the body, `dt` seconds after the prediction.
+    pub fn state_after(&self, dt: f64) -> (DVec3, DVec3) {
+        kepler::propagate(self.mu, self.position, self.velocity, dt)
+    }
+
+    /// Height (m) of the periapsis above the body's surface.
+    pub fn periapsis_altitude(&self) -> f64 {
+        self.conic.periapsis - self.radius
+    }
+
+    /// Height (m) of the apoapsis above the body's surface, for closed orbits.
+    pub fn apoapsis_altitude(&self) -> Option<f64> {
+        self.conic.apoapsis().map(|apoapsis| apoapsis - self.radius)
+    }
+}
+
+fn predict_orbits(
+    orrery: Res<Orrery>,
+    time: SimTime,
+    objects: Query<(
+        &PreciseTransform,
+        &Velocity,
+        &WithinSoi,
+        &mut PredictedOrbit,
+    )>,
+    planets: Query<(&Celestial, &PreciseTransform)>,
+) {
+    let epoch = time.epoch();
+    for (ptf, velocity, soi, mut orbit) in objects {
+        let Ok((planet, planet_ptf)) = planets.get(soi.0) else {
+            continue;
+        };
+        let (Some(body), Some(planet_velocity)) = (
+            orrery.get_body(&planet.0),
+            orrery.solve_absolute_velocity(&planet.0, epoch),
+        ) else {
+            continue;
+        };
+        let position = (ptf.translation_mm - planet_ptf.translation_mm).to_meters_64();
+        let velocity = velocity.0 - planet_velocity;
+        let conic = Conic::from_state(body.mu, position, velocity);
+        let since = kepler::time_since_periapsis(body.mu, position, velocity);
+        let time_to_periapsis = if conic.apoapsis().is_some() {
+            let period = 2.0 * std::f64::consts::PI / conic.mean_motion(body.mu);
+            Some(if since > 0.0 { period - since } else { -since })
+        } else {
+            (since < 0.0).then_some(-since)
+        };
+        *orbit = PredictedOrbit {
+            mu: body.mu,
+            radius: body.radius,
+            position,
+            velocity,
+            conic,
+            time_to_periapsis,
+        };
+    }
+}
+
+/// Draws the predicted orbit of the focused object, one period ahead or a day for open orbits.
+fn draw_predicted_orbit(
+    mut gizmos: Gizmos,
+    origin: Res<FloatingOrigin>,
+    vessel: Single<(&PredictedOrbit, &WithinSoi), With<CameraFocus>>,
+    planets: Query<&PreciseTransform, With<Celestial>>,
+) {
+    let (orbit, soi) = vessel.into_inner();
+    let Ok(planet_ptf) = planets.get(soi.0) else {
+        return;
+    };
+    if orbit.mu <= 0.0 {
+        return;
+    }
+    let span = match orbit.conic.apoapsis() {
+        Some(_) => 2.0 * std::f64::consts::PI / orbit.conic.mean_motion(orbit.mu),
+        None => OPEN_PATH_SPAN,
+    };
+    let points = (0..=PATH_POINTS).map(|i| {
+        let (position, _) = orbit.state_after(span * i as f64 / PATH_POINTS as f64);
+        origin.project_loc(planet_ptf.translation_mm + position.to_millimeters())
+    });
+    gizmos.linestrip(points, Color::srgb(0.3, 0.8, 1.0));
+}
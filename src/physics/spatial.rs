@@ -0,0 +1,154 @@
+use std::num::NonZero;
+
+use bevy::{math::I64Vec3, prelude::*};
+use kiddo::{ImmutableKdTree, SquaredEuclidean};
+
+use crate::{GameState, physics::RigidBody, precision::PreciseTransform};
+
+pub(super) fn run_spatial(app: &mut App) {
+    app.init_resource::<SpatialIndex>().add_systems(
+        FixedPreUpdate,
+        rebuild_spatial_index.run_if(in_state(GameState::Game)),
+    );
+}
+
+/// A spatial index over the positions of all rigid bodies, rebuilt at the start of every tick.
+///
+/// Positions are those at the time of the rebuild, so they may lag behind by up to one tick of motion.
+#[derive(Resource)]
+pub struct SpatialIndex {
+    tree: ImmutableKdTree<f64, 3>,
+    entries: Vec<(Entity, I64Vec3)>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+/// A result of a proximity query.
+#[derive(Clone, Copy, Debug)]
+pub struct Neighbour {
+    pub entity: Entity,
+    /// Distance from the query point, in millimeters.
+    pub distance_mm: f64,
+}
+
+impl SpatialIndex {
+    /// Builds an index over the given entities and their positions in millimeters.
+    pub fn new(entries: Vec<(Entity, I64Vec3)>) -> Self {
+        let points = entries
+            .iter()
+            .map(|(_, posn)| posn.as_dvec3().to_array())
+            .collect::<Vec<_>>();
+        Self {
+            tree: ImmutableKdTree::new_from_slice(&points),
+            entries,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates through the indexed entities and their positions in millimeters.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, I64Vec3)> + '_ {
+        self.entries.iter().copied()
+    }
+
+    /// Finds the `n` entities nearest to a point (mm), sorted by distance.
+    pub fn nearest_n(&self, point: I64Vec3, n: usize) -> Vec<Neighbour> {
+        let Some(n) = NonZero::new(n.min(self.len())) else {
+            return vec![];
+        };
+        let query = point.as_dvec3().to_array();
+        let found = self.tree.nearest_n::<SquaredEuclidean>(&query, n);
+        self.neighbours(point, found.into_iter().map(|nb| nb.item))
+    }
+
+    /// Finds all entities within `radius_mm` of a point (mm), sorted by distance.
+    pub fn within_radius(&self, point: I64Vec3, radius_mm: f64) -> Vec<Neighbour> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let query = point.as_dvec3().to_array();
+        let found = self
+            .tree
+            .within_unsorted::<SquaredEuclidean>(&query, radius_mm * radius_mm);
+        let mut neighbours = self.neighbours(point, found.into_iter().map(|nb| nb.item));
+        // the tree works in f64, so the boundary is re-checked exactly
+        neighbours.retain(|nb| nb.distance_mm <= radius_mm);
+        neighbours
+    }
+
+    /// Resolves tree items into neighbours, with distances computed from the exact integer positions.
+    fn neighbours(&self, point: I64Vec3, items: impl Iterator<Item = u64>) -> Vec<Neighbour> {
+        let mut neighbours = items
+            .map(|item| {
+                let (entity, translation_mm) = self.entries[item as usize];
+                Neighbour {
+                    entity,
+                    distance_mm: (translation_mm - point).as_dvec3().length(),
+                }
+            })
+            .collect::<Vec<_>>();
+        neighbours.sort_unstable_by(|a, b| a.distance_mm.total_cmp(&b.distance_mm));
+        neighbours
+    }
+}
+
+fn rebuild_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    bodies: Query<(Entity, &PreciseTransform), With<RigidBody>>,
+) {
+    *index = SpatialIndex::new(
+        bodies
+            .iter()
+            .map(|(ent, ptf)| (ent, ptf.translation_mm))
+            .collect(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proximity_queries() {
+        assert!(
+            SpatialIndex::default()
+                .nearest_n(I64Vec3::ZERO, 3)
+                .is_empty()
+        );
+
+        let far = 90_000_000_000_000; // ~0.6 AU, in millimeters
+        let entries = (0..100)
+            .map(|i| {
+                (
+                    Entity::from_raw(i),
+                    I64Vec3::new(far + i as i64 * 1_000, far, -far),
+                )
+            })
+            .collect::<Vec<_>>();
+        let index = SpatialIndex::new(entries);
+        let query = I64Vec3::new(far + 10_400, far, -far);
+
+        let nearest = index.nearest_n(query, 2);
+        assert_eq!(nearest.len(), 2);
+        assert_eq!(nearest[0].entity, Entity::from_raw(10));
+        assert_eq!(nearest[1].entity, Entity::from_raw(11));
+        assert_eq!(nearest[0].distance_mm, 400.0);
+
+        let within = index.within_radius(query, 2_000.0);
+        let found = within
+            .iter()
+            .map(|nb| nb.entity.index())
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![10, 11, 9, 12]);
+    }
+}
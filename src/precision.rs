@@ -9,8 +9,9 @@ pub struct PrecisionPlugin;
 
 impl Plugin for PrecisionPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(FloatingOrigin(PreciseTransform::default()));
-        app.add_systems(FixedPreUpdate, float_origin);
+        app.insert_resource(FloatingOrigin(PreciseTransform::default()))
+            .init_resource::<FloatOriginStrategy>();
+        app.add_systems(FixedPreUpdate, (float_origin, propagate_precise_transforms));
     }
 }
 
@@ -38,18 +39,120 @@ fn float_origin(
     });
 }
 
+/// Computes the world pose of every entity with a [`PreciseGlobalTransform`], from the [`PreciseTransform`] of its
+/// root and the local `Transform` of each entity below it.
+fn propagate_precise_transforms(
+    mut roots: Query<
+        (
+            &PreciseTransform,
+            &mut PreciseGlobalTransform,
+            Option<&Children>,
+        ),
+        Without<ChildOf>,
+    >,
+    mut descendants: Query<
+        (&Transform, &mut PreciseGlobalTransform, Option<&Children>),
+        With<ChildOf>,
+    >,
+) {
+    let mut stack = vec![];
+    for (ptf, mut global, children) in &mut roots {
+        global.0 = *ptf;
+        stack.extend(
+            children
+                .into_iter()
+                .flatten()
+                .map(|&child| (child, *global)),
+        );
+        // entities without a precise world pose cut their subtree off
+        while let Some((entity, parent)) = stack.pop() {
+            let Ok((tf, mut global, children)) = descendants.get_mut(entity) else {
+                continue;
+            };
+            *global = parent.mul_transform(tf);
+            stack.extend(
+                children
+                    .into_iter()
+                    .flatten()
+                    .map(|&child| (child, *global)),
+            );
+        }
+    }
+}
+
 #[derive(Resource)]
 /// The floating origin for rendering the high-precision world. This must be externally updated.
 pub struct FloatingOrigin(pub PreciseTransform);
 
-#[derive(Component, Default, Clone, Copy, Serialize, Deserialize)]
-#[require(Transform)]
+/// Distance (m) from the floating origin within which rendered positions keep sub-millimeter precision: below it,
+/// consecutive f32 values are at most 2^-11 m (≈0.49 mm) apart.
+pub const PRECISE_DISTANCE: f64 = 8192.0;
+
+/// Distance (m) from the camera within which rendered objects are kept within [`PRECISE_DISTANCE`] of the floating
+/// origin.
+pub const PRECISE_VIEW_DISTANCE: f64 = 4096.0;
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+/// Where the floating origin is put. Whatever the strategy, it is moved to the camera whenever the camera would be
+/// further than [`PRECISE_DISTANCE`] less [`PRECISE_VIEW_DISTANCE`] from it, so that what's rendered around the camera
+/// keeps sub-millimeter precision.
+pub enum FloatOriginStrategy {
+    /// At the camera, every tick.
+    Camera,
+    /// At the focused vessel, moved only once the vessel drifts further than a distance (m) from it.
+    Focus { recentre_distance: f64 },
+    /// On the surface of the nearest planet, under the camera.
+    Surface,
+}
+
+impl Default for FloatOriginStrategy {
+    fn default() -> Self {
+        Self::Focus {
+            recentre_distance: 1000.0,
+        }
+    }
+}
+
+#[derive(Component, Default, Clone, Copy, Debug, Serialize, Deserialize)]
+#[require(Transform, PreciseGlobalTransform)]
 /// A high-precision transform, in *millimeters*.
 pub struct PreciseTransform {
     pub translation_mm: I64Vec3,
     pub rotation: DQuat,
 }
 
+#[derive(Component, Default, Clone, Copy, Debug)]
+/// The high-precision world pose of an entity, propagated down the hierarchy from the [`PreciseTransform`] of its
+/// root every fixed tick. Scale is ignored.
+pub struct PreciseGlobalTransform(pub PreciseTransform);
+
+impl PreciseGlobalTransform {
+    /// The world pose of a child at a local transform (in meters) from this one.
+    pub fn mul_transform(&self, local: &Transform) -> Self {
+        Self(PreciseTransform {
+            translation_mm: self.transform_point(local.translation.as_dvec3()),
+            rotation: (self.0.rotation * local.rotation.as_dquat()).normalize(),
+        })
+    }
+
+    /// The world location (in mm) of a point, given in meters in this entity's axes.
+    pub fn transform_point(&self, local: DVec3) -> I64Vec3 {
+        self.0
+            .translation_mm
+            .saturating_add((self.0.rotation * local).to_millimeters())
+    }
+
+    /// The world direction of a vector given in this entity's axes.
+    pub fn transform_direction(&self, local: DVec3) -> DVec3 {
+        self.0.rotation * local
+    }
+
+    /// The offset (in meters) of this entity from another one, in world axes.
+    pub fn offset_from(&self, other: &PreciseGlobalTransform) -> DVec3 {
+        (self.0.translation_mm - other.0.translation_mm).to_meters_64()
+    }
+}
+
 impl PreciseTransform {
     pub fn look_at(&mut self, target: I64Vec3, up: DVec3) {
         self.look_to(
@@ -159,3 +262,118 @@ impl FloatingOrigin {
         rotated_translation.as_vec3()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precise_transforms_propagate_down_the_hierarchy() {
+        let mut app = App::new();
+        app.add_systems(Update, propagate_precise_transforms);
+        let root = app
+            .world_mut()
+            .spawn(PreciseTransform {
+                translation_mm: I64Vec3::new(1_000_000_000_000, 0, 0),
+                rotation: DQuat::from_rotation_y(std::f64::consts::FRAC_PI_2),
+            })
+            .id();
+        let part = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(0.0, 0.0, -2.0)
+                    .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+                PreciseGlobalTransform::default(),
+                ChildOf(root),
+            ))
+            .id();
+        let module = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(0.0, 0.5, 0.0),
+                PreciseGlobalTransform::default(),
+                ChildOf(part),
+            ))
+            .id();
+        app.update();
+
+        let world = app.world();
+        let global = |entity| world.get::<PreciseGlobalTransform>(entity).unwrap();
+        // the root's -Z points along -X, and the part's +Y along the root's -Z
+        assert_eq!(
+            global(part).0.translation_mm,
+            I64Vec3::new(999_999_998_000, 0, 0)
+        );
+        assert_eq!(
+            global(module).0.translation_mm,
+            I64Vec3::new(999_999_997_500, 0, 0)
+        );
+        assert!(
+            global(module)
+                .transform_direction(DVec3::Y)
+                .abs_diff_eq(DVec3::NEG_X, 1e-6)
+        );
+        assert!(
+            global(module)
+                .offset_from(global(root))
+                .abs_diff_eq(DVec3::new(-2.5, 0.0, 0.0), 1e-9)
+        );
+    }
+
+    #[test]
+    fn projections_are_sub_millimeter_near_origin() {
+        let origin = FloatingOrigin(PreciseTransform {
+            translation_mm: I64Vec3::new(1_496_000_000_000_000, -7_000_000_123, 42_000_000_017),
+            rotation: DQuat::from_euler(EulerRot::YXZ, 0.3, -1.1, 2.0),
+        });
+        let inverse = origin.0.rotation.inverse();
+        let mut worst = 0.0f64;
+        // points all the way out to the precise distance, in every direction and with odd millimeters
+        for i in 0..1000 {
+            let direction =
+                DQuat::from_euler(EulerRot::YXZ, i as f64 * 0.37, i as f64 * 0.11, 0.0) * DVec3::Z;
+            let distance = PRECISE_DISTANCE * (1.0 - (i as f64 * 0.618).fract() * 0.5) - 0.001;
+            let offset_mm = (direction * distance).to_millimeters() + I64Vec3::new(1, -3, 7);
+            let projected = origin.project(&PreciseTransform {
+                translation_mm: origin.0.translation_mm + offset_mm,
+                rotation: DQuat::IDENTITY,
+            });
+            let exact = inverse * offset_mm.to_meters_64();
+            worst = worst.max(
+                (projected.translation.as_dvec3() - exact)
+                    .abs()
+                    .max_element(),
+            );
+        }
+        assert!(worst < 0.5e-3, "off by up to {worst} m");
+    }
+
+    #[test]
+    fn sub_millimeter_precision_near_origin() {
+        let below = (PRECISE_DISTANCE as f32).next_down();
+        assert!(PRECISE_DISTANCE as f32 - below < 1e-3);
+
+        // far out in the system, with an arbitrary orientation
+        let origin = FloatingOrigin(PreciseTransform {
+            translation_mm: I64Vec3::new(1_496_000_000_000_000, -7_000_000_123, 42_000_000_017),
+            rotation: DQuat::from_euler(EulerRot::YXZ, 0.3, -1.1, 2.0),
+        });
+        for offset in [
+            DVec3::new(0.0005, 0.0, 0.0),
+            DVec3::new(-4000.123, 2500.456, 3000.789),
+            DVec3::splat(PRECISE_DISTANCE / 3f64.sqrt() - 1.0),
+        ] {
+            let loc = origin.0.translation_mm + offset.to_millimeters();
+            let projected = PreciseTransform {
+                translation_mm: loc,
+                rotation: DQuat::IDENTITY,
+            };
+            let back = origin.deproject(&origin.project(&projected));
+            assert!(
+                (back.translation_mm - loc).abs().max_element() <= 1,
+                "{offset} came back {:?} mm off",
+                back.translation_mm - loc
+            );
+        }
+    }
+}
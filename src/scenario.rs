@@ -0,0 +1,161 @@
+mod scenario_cfg;
+
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+use smol_str::SmolStr;
+
+use crate::{
+    GameState,
+    assets::TomlAssetLoader,
+    launch_option,
+    orrery::StarSystems,
+    physics::SimClock,
+    vessel::{LoadedVessels, SpawnVesselEvent, load_vessels},
+};
+
+use scenario_cfg::ScenarioCfg;
+
+/// Environment variable naming the scenario to start, overridden by the `--scenario` argument.
+const SCENARIO_VAR: &str = "TOY_SIM_SCENARIO";
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_loading_state(
+            LoadingStateConfig::new(GameState::Loading).load_collection::<ScenarioAssets>(),
+        )
+        .register_asset_loader(TomlAssetLoader::<ScenarioCfg>::new("scenario.toml"))
+        .init_asset::<ScenarioCfg>()
+        .add_event::<StartScenario>()
+        .init_resource::<Scenarios>()
+        .add_systems(OnEnter(GameState::Menu), list_scenarios)
+        .add_systems(Update, start_scenario.run_if(in_state(GameState::Menu)))
+        .add_systems(
+            OnEnter(GameState::Game),
+            (start_clock, spawn_scenario.after(load_vessels)),
+        );
+    }
+}
+
+/// Handles to every scenario in the `scenarios` folder.
+#[derive(Resource, AssetCollection)]
+struct ScenarioAssets {
+    #[asset(path = "scenarios", collection(typed))]
+    scenarios: Vec<Handle<ScenarioCfg>>,
+}
+
+/// The scenarios that can be started, and the one that was.
+#[derive(Resource, Debug, Default)]
+pub struct Scenarios {
+    /// Names of every loaded scenario, sorted.
+    pub available: Vec<SmolStr>,
+    pub active: Option<SmolStr>,
+}
+
+/// Starts the game with a scenario, given by name.
+#[derive(Event, Clone, Debug)]
+pub struct StartScenario(pub SmolStr);
+
+/// Finds a loaded scenario by name, ignoring case.
+fn find_scenario<'a>(
+    assets: &ScenarioAssets,
+    cfgs: &'a Assets<ScenarioCfg>,
+    name: &str,
+) -> Option<&'a ScenarioCfg> {
+    assets
+        .scenarios
+        .iter()
+        .filter_map(|handle| cfgs.get(handle))
+        .find(|cfg| cfg.name.eq_ignore_ascii_case(name))
+}
+
+/// Lists the loaded scenarios, and starts the one asked for with `--scenario <name>` or in the environment, if any.
+fn list_scenarios(
+    assets: Res<ScenarioAssets>,
+    cfgs: Res<Assets<ScenarioCfg>>,
+    mut scenarios: ResMut<Scenarios>,
+    mut start: EventWriter<StartScenario>,
+) {
+    scenarios.available = assets
+        .scenarios
+        .iter()
+        .filter_map(|handle| cfgs.get(handle))
+        .map(|cfg| cfg.name.clone())
+        .collect();
+    scenarios.available.sort();
+    if let Some(name) = launch_option("scenario", SCENARIO_VAR) {
+        start.write(StartScenario(name));
+    }
+}
+
+/// Sets up the star system and the clock of the picked scenario, and starts the game.
+fn start_scenario(
+    mut events: EventReader<StartScenario>,
+    assets: Res<ScenarioAssets>,
+    cfgs: Res<Assets<ScenarioCfg>>,
+    (mut scenarios, mut systems, mut clock): (
+        ResMut<Scenarios>,
+        ResMut<StarSystems>,
+        ResMut<SimClock>,
+    ),
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(StartScenario(name)) = events.read().last() else {
+        return;
+    };
+    let Some(cfg) = find_scenario(&assets, &cfgs, name) else {
+        error!("cannot start unknown scenario {name}");
+        return;
+    };
+    info!("starting scenario {}", cfg.name);
+    if let Some(system) = &cfg.system {
+        systems.active = system.clone();
+    }
+    clock.start = cfg.start();
+    scenarios.active = Some(cfg.name.clone());
+    next_state.set(GameState::Game);
+}
+
+/// Pins the scenario's start epoch to the current tick.
+fn start_clock(mut clock: ResMut<SimClock>, time: Res<Time<Fixed>>) {
+    clock.started_at = time.elapsed_secs_f64();
+}
+
+/// Spawns the vessels of the active scenario.
+fn spawn_scenario(
+    assets: Res<ScenarioAssets>,
+    cfgs: Res<Assets<ScenarioCfg>>,
+    scenarios: Res<Scenarios>,
+    vessels: Res<LoadedVessels>,
+    mut spawn: EventWriter<SpawnVesselEvent>,
+) {
+    let Some(cfg) = scenarios
+        .active
+        .as_ref()
+        .and_then(|name| find_scenario(&assets, &cfgs, name))
+    else {
+        return;
+    };
+    let states = match cfg.initial_states() {
+        Ok(states) => states,
+        Err(err) => {
+            error!("cannot spawn the vessels of {}: {err:#}", cfg.name);
+            return;
+        }
+    };
+    let focus = cfg.focus();
+    for (i, (vessel, initial)) in cfg.vessels.iter().zip(states).enumerate() {
+        let Some(vessel_cfg) = vessels.vessels.get(&vessel.class) else {
+            error!("{} has unknown vessel class {}", vessel.name, vessel.class);
+            continue;
+        };
+        spawn.write(SpawnVesselEvent {
+            cfg: vessel_cfg.clone(),
+            name: vessel.name.clone(),
+            initial,
+            camera_focus: i == focus,
+            preserved: None,
+        });
+    }
+}
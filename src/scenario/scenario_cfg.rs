@@ -0,0 +1,244 @@
+use bevy::{asset::Asset, math::DVec3, reflect::TypePath};
+use hifitime::Epoch;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use crate::{
+    orrery::{Angle, AngleUnit, Orbit, de_angle, de_distance},
+    physics::geodesy::{Attitude, Geodetic},
+    vessel::InitialState,
+};
+
+/// A starting point for the game: a star system, an epoch, and the vessels in it.
+#[derive(Asset, TypePath, Clone, Debug, Serialize, Deserialize)]
+pub struct ScenarioCfg {
+    pub name: SmolStr,
+    /// The star system to simulate, or the one asked for at launch if not given.
+    #[serde(default)]
+    pub system: Option<SmolStr>,
+    /// Epoch at the start of the scenario, e.g. "2100-01-01T00:00:00 UTC".
+    #[serde(default)]
+    pub epoch: Option<Epoch>,
+    /// Unit of angles given as bare numbers.
+    #[serde(default)]
+    pub angle_unit: AngleUnit,
+    pub vessels: Vec<ScenarioVessel>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScenarioVessel {
+    pub name: SmolStr,
+    /// Name of the vessel configuration.
+    pub class: SmolStr,
+    /// Whether the camera follows this vessel. If none does, the first one is followed.
+    #[serde(default)]
+    pub focus: bool,
+    #[serde(flatten)]
+    pub initial: InitialStateCfg,
+}
+
+/// Where a vessel starts, and how it moves.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitialStateCfg {
+    /// On an orbit around a body, given by its elements in the inertial frame. The mean anomaly is at the start of
+    /// the scenario, unless the orbit has an epoch of its own.
+    Orbit {
+        body: SmolStr,
+        #[serde(flatten)]
+        elements: Orbit,
+    },
+    /// On a circular orbit around a body.
+    Circular(CircularCfg),
+    /// Over a body, relative to its surface.
+    Surface(SurfaceCfg),
+    /// Next to a vessel listed before this one.
+    Relative(RelativeCfg),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircularCfg {
+    pub body: SmolStr,
+    /// Above the body's radius.
+    #[serde(deserialize_with = "de_distance")]
+    pub altitude: f64,
+    /// Relative to the body's equator.
+    #[serde(deserialize_with = "de_angle", default)]
+    pub inclination: Angle,
+    /// Where the orbit crosses the equator northwards, at the start.
+    #[serde(deserialize_with = "de_angle", default)]
+    pub longitude: Angle,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SurfaceCfg {
+    pub body: SmolStr,
+    #[serde(deserialize_with = "de_angle")]
+    pub latitude: Angle,
+    #[serde(deserialize_with = "de_angle")]
+    pub longitude: Angle,
+    /// Above the body's radius.
+    #[serde(deserialize_with = "de_distance", default)]
+    pub altitude: f64,
+    /// Clockwise from north.
+    #[serde(deserialize_with = "de_angle", default)]
+    pub heading: Angle,
+    #[serde(deserialize_with = "de_angle", default)]
+    pub pitch: Angle,
+    #[serde(deserialize_with = "de_angle", default)]
+    pub roll: Angle,
+    /// Velocity (m/s) relative to the surface, towards north, east and down.
+    #[serde(default)]
+    pub velocity_ned: [f64; 3],
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RelativeCfg {
+    /// Name of the other vessel.
+    pub vessel: SmolStr,
+    /// Offset (m) from the other vessel, in its axes: right, up and backwards.
+    #[serde(default)]
+    pub offset: [f64; 3],
+    /// Velocity (m/s) relative to the other vessel, in its axes.
+    #[serde(default)]
+    pub velocity: [f64; 3],
+}
+
+impl ScenarioCfg {
+    /// The initial state of every vessel, in order. Fails if a vessel is placed relative to one that isn't listed
+    /// before it.
+    pub fn initial_states(&self) -> anyhow::Result<Vec<InitialState>> {
+        let rad = |mut angle: Angle| {
+            angle.resolve(self.angle_unit);
+            angle.rad()
+        };
+        let mut states: Vec<InitialState> = Vec::with_capacity(self.vessels.len());
+        for vessel in &self.vessels {
+            let state = match &vessel.initial {
+                InitialStateCfg::Orbit { body, elements } => {
+                    let mut orbit = *elements;
+                    for angle in [
+                        &mut orbit.inclination,
+                        &mut orbit.ascending_node,
+                        &mut orbit.arg_of_pericenter,
+                        &mut orbit.mean_anomaly,
+                    ] {
+                        angle.resolve(self.angle_unit);
+                    }
+                    if orbit.epoch == 0.0 {
+                        orbit.epoch = self.start().to_mjd_utc_days();
+                    }
+                    InitialState::Orbit {
+                        body: body.clone(),
+                        orbit,
+                    }
+                }
+                InitialStateCfg::Circular(circular) => InitialState::Circular {
+                    body: circular.body.clone(),
+                    altitude: circular.altitude,
+                    inclination: rad(circular.inclination),
+                    longitude: rad(circular.longitude),
+                },
+                InitialStateCfg::Surface(surface) => InitialState::Surface {
+                    body: surface.body.clone(),
+                    position: Geodetic {
+                        latitude: rad(surface.latitude),
+                        longitude: rad(surface.longitude),
+                        altitude: surface.altitude,
+                    },
+                    attitude: Attitude {
+                        heading: rad(surface.heading),
+                        pitch: rad(surface.pitch),
+                        roll: rad(surface.roll),
+                    },
+                    velocity_ned: DVec3::from_array(surface.velocity_ned),
+                },
+                InitialStateCfg::Relative(relative) => {
+                    let Some(index) = self.vessels[..states.len()]
+                        .iter()
+                        .position(|other| other.name == relative.vessel)
+                    else {
+                        anyhow::bail!(
+                            "{} is placed next to {}, which isn't listed before it",
+                            vessel.name,
+                            relative.vessel
+                        );
+                    };
+                    InitialState::Relative {
+                        to: Box::new(states[index].clone()),
+                        offset: DVec3::from_array(relative.offset),
+                        velocity: DVec3::from_array(relative.velocity),
+                    }
+                }
+            };
+            states.push(state);
+        }
+        Ok(states)
+    }
+
+    /// Epoch at the start of the scenario.
+    pub fn start(&self) -> Epoch {
+        self.epoch.unwrap_or(Epoch::from_tai_seconds(0.0))
+    }
+
+    /// Index of the vessel the camera follows.
+    pub fn focus(&self) -> usize {
+        self.vessels
+            .iter()
+            .position(|vessel| vessel.focus)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, Result};
+
+    #[test]
+    fn every_asset_scenario_loads() -> Result<()> {
+        for entry in std::fs::read_dir("assets/scenarios")? {
+            let path = entry?.path();
+            let cfg: ScenarioCfg = toml::from_str(&std::fs::read_to_string(&path)?)
+                .with_context(|| format!("loading {}", path.display()))?;
+            assert_eq!(cfg.initial_states()?.len(), cfg.vessels.len());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn relative_placement_needs_an_earlier_vessel() -> Result<()> {
+        let target = r#"
+[[vessels]]
+name = "Target"
+class = "dummy"
+surface = { body = "Pannea", latitude = 90, longitude = 0, heading = "1.0 rad" }
+"#;
+        let chaser = r#"
+[[vessels]]
+name = "Chaser"
+class = "dummy"
+relative = { vessel = "Target", offset = [0.0, 0.0, 50.0] }
+"#;
+        let scenario = |vessels: &[&str]| -> Result<ScenarioCfg> {
+            Ok(toml::from_str(&format!(
+                "name = \"test\"\n{}",
+                vessels.concat()
+            ))?)
+        };
+        assert!(scenario(&[chaser, target])?.initial_states().is_err());
+
+        let states = scenario(&[target, chaser])?.initial_states()?;
+        let InitialState::Surface {
+            position, attitude, ..
+        } = &states[0]
+        else {
+            panic!("{:?} isn't on the surface", states[0]);
+        };
+        // bare angles are in degrees
+        assert!((position.latitude - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        assert_eq!(attitude.heading, 1.0);
+        assert!(matches!(states[1], InitialState::Relative { .. }));
+        Ok(())
+    }
+}
@@ -1,30 +1,41 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use bevy::prelude::*;
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
 use bevy_asset_loader::prelude::*;
 use smol_str::SmolStr;
 
 use crate::{
     GameState,
     assets::TomlAssetLoader,
-    physics::RigidBody,
+    camera::CameraFocus,
+    physics::{AngularVelocity, RigidBody, Velocity},
+    precision::{PreciseGlobalTransform, PreciseTransform},
     vessel::{
+        modules::{Module, envelope::GasEnvelope, reactor::NuclearReactor},
         part_cfg::PartCfg,
+        spawn::{PreservedPart, PreservedState},
         vessel_cfg::VesselCfg,
     },
 };
 
 mod consumable;
+mod damage;
+mod hull;
 mod modules;
 mod part_cfg;
 mod spawn;
+mod thermal;
 
 mod controls;
 mod vessel_cfg;
 
 pub use consumable::ConsumableTanks;
 pub use controls::VesselControls;
+pub use damage::{DestroyPartEvent, PartFailure};
+pub use hull::PartHull;
 pub use modules::thruster::Thruster;
+pub use spawn::{InitialState, SpawnVesselEvent};
+pub use thermal::{Ablator, PartThermal};
 
 pub struct VesselsPlugin;
 
@@ -37,32 +48,201 @@ impl Plugin for VesselsPlugin {
         .register_asset_loader(TomlAssetLoader::<PartCfg>::new("part.toml"))
         .init_asset::<VesselCfg>()
         .init_asset::<PartCfg>()
+        .init_resource::<HotReload>()
         .add_systems(OnEnter(GameState::Game), load_vessels)
+        .add_systems(
+            Update,
+            reload_vessels
+                .run_if(in_state(GameState::Game))
+                .run_if(resource_exists::<LoadedVessels>),
+        )
         .add_plugins((
             spawn::run_spawn,
             modules::start_modules,
             controls::run_controls,
+            thermal::run_thermal,
+            hull::run_hull,
+            damage::run_damage,
         ));
     }
 }
 
-fn load_vessels(
+pub(crate) fn load_vessels(
     mut commands: Commands,
     assets: Res<VesselAssets>,
     vessels: Res<Assets<VesselCfg>>,
     parts: Res<Assets<PartCfg>>,
 ) {
     // TODO: validation!!!
-    let mut loaded = LoadedVessels::default();
-    for vessel in assets.vessels.iter() {
-        let vessel = vessels.get(vessel).unwrap();
-        loaded.vessels.insert(vessel.name.clone(), vessel.clone());
+    commands.insert_resource(LoadedVessels::collect(&assets, &vessels, &parts));
+}
+
+/// Picks up edits to vessel and part configurations, and respawns the vessels they affect if asked to. Respawned
+/// vessels keep their state, down to their parts and modules, and parts that were destroyed stay so.
+fn reload_vessels(
+    mut commands: Commands,
+    (mut vessel_events, mut part_events): (
+        EventReader<AssetEvent<VesselCfg>>,
+        EventReader<AssetEvent<PartCfg>>,
+    ),
+    (assets, vessel_cfgs, part_cfgs): (
+        Res<VesselAssets>,
+        Res<Assets<VesselCfg>>,
+        Res<Assets<PartCfg>>,
+    ),
+    (mut loaded, settings): (ResMut<LoadedVessels>, Res<HotReload>),
+    vessels: Query<RespawnedVessel>,
+    (parts, modules): (Query<RespawnedPart>, Query<RespawnedModule>),
+    mut spawn: EventWriter<SpawnVesselEvent>,
+) {
+    let changed_vessels = vessel_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => vessel_cfgs.get(*id).map(|cfg| cfg.name.clone()),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    let changed_parts = part_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => part_cfgs.get(*id).map(|cfg| cfg.name.clone()),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    if changed_vessels.is_empty() && changed_parts.is_empty() {
+        return;
     }
-    for part in assets.parts.iter() {
-        let part = parts.get(part).unwrap();
-        loaded.parts.insert(part.name.clone(), part.clone());
+    info!("reloading vessels {changed_vessels:?} and parts {changed_parts:?}");
+    let previous = std::mem::replace(
+        &mut *loaded,
+        LoadedVessels::collect(&assets, &vessel_cfgs, &part_cfgs),
+    );
+    if !settings.respawn_vessels {
+        return;
+    }
+
+    for (
+        entity,
+        vessel,
+        location,
+        velocity,
+        angular_velocity,
+        controls,
+        tanks,
+        children,
+        focused,
+    ) in &vessels
+    {
+        let Some(cfg) = loaded.vessels.get(&vessel.class_name) else {
+            continue;
+        };
+        let affected = changed_vessels.contains(&cfg.name)
+            || cfg
+                .parts
+                .iter()
+                .any(|part| changed_parts.contains(&part.proto));
+        if !affected {
+            continue;
+        }
+
+        let children = children.into_iter().flatten();
+        let mut ids = EntityHashMap::default();
+        let mut kept = BTreeMap::new();
+        for (part_entity, part, thermal, hull) in parts.iter_many(children.clone()) {
+            ids.insert(part_entity, part.id.clone());
+            kept.insert(
+                part.id.clone(),
+                PreservedPart {
+                    temperature: thermal.temperature,
+                    strained: hull.strained,
+                    ..default()
+                },
+            );
+        }
+        // modules are spawned in the order of their part's configuration
+        for (module, ablator, envelope, reactor) in modules.iter_many(children) {
+            let Some(part) = ids.get(&module.part).and_then(|id| kept.get_mut(id)) else {
+                continue;
+            };
+            if let Some(ablator) = ablator {
+                part.ablators.push(ablator.mass);
+            }
+            if let Some(envelope) = envelope {
+                part.envelopes.push(envelope.gas_mass);
+            }
+            if let Some(reactor) = reactor {
+                part.reactors
+                    .push((reactor.current_throttle, reactor.desired_throttle));
+            }
+        }
+        let destroyed = previous
+            .vessels
+            .get(&vessel.class_name)
+            .into_iter()
+            .flat_map(|cfg| &cfg.parts)
+            .filter(|part| !kept.contains_key(&part.id))
+            .map(|part| part.id.clone())
+            .collect();
+
+        commands.entity(entity).despawn();
+        spawn.write(SpawnVesselEvent {
+            cfg: cfg.clone(),
+            name: vessel.vessel_name.clone(),
+            initial: InitialState::Inertial {
+                transform: *location,
+                velocity: velocity.0,
+            },
+            camera_focus: focused,
+            preserved: Some(PreservedState {
+                angular_velocity: angular_velocity.0,
+                dir_fbw_target: controls.dir_fbw_target,
+                rot_fbw_target: controls.rot_fbw_target,
+                throttle: controls.raw_throttle,
+                steering: controls.raw_steering,
+                buoyancy: controls.raw_buoyancy,
+                consumables: tanks
+                    .iter()
+                    .map(|(cons, (amount, _))| (cons, amount))
+                    .collect(),
+                parts: kept,
+                destroyed,
+            }),
+        });
     }
-    commands.insert_resource(loaded);
+}
+
+type RespawnedVessel = (
+    Entity,
+    &'static Vessel,
+    &'static PreciseTransform,
+    &'static Velocity,
+    &'static AngularVelocity,
+    &'static VesselControls,
+    &'static ConsumableTanks,
+    Option<&'static Children>,
+    Has<CameraFocus>,
+);
+
+type RespawnedPart = (
+    Entity,
+    &'static Part,
+    &'static PartThermal,
+    &'static PartHull,
+);
+
+type RespawnedModule = (
+    &'static Module,
+    Option<&'static Ablator>,
+    Option<&'static GasEnvelope>,
+    Option<&'static NuclearReactor>,
+);
+
+/// How edits to configuration files are applied while the game runs.
+#[derive(Resource, Default)]
+pub struct HotReload {
+    /// Whether vessels are respawned from their edited configuration, keeping their state but for their fly-by-wire
+    /// controllers and control surface deflections. Otherwise, only vessels spawned afterwards use it.
+    pub respawn_vessels: bool,
 }
 
 #[derive(Component)]
@@ -72,17 +252,209 @@ pub struct Vessel {
     pub vessel_name: SmolStr,
 }
 
+/// A part of a vessel, spawned as a child of the vessel entity.
+#[derive(Component)]
+#[require(PreciseGlobalTransform)]
+pub struct Part {
+    pub id: SmolStr,
+    pub proto: SmolStr,
+    /// Current mass, in kg.
+    pub mass: f64,
+}
+
 #[derive(Resource, Default)]
 pub struct LoadedVessels {
     pub vessels: BTreeMap<SmolStr, VesselCfg>,
     pub parts: BTreeMap<SmolStr, PartCfg>,
 }
 
+impl LoadedVessels {
+    /// Gathers every loaded vessel and part configuration by name.
+    fn collect(
+        assets: &VesselAssets,
+        vessels: &Assets<VesselCfg>,
+        parts: &Assets<PartCfg>,
+    ) -> Self {
+        let mut loaded = Self::default();
+        for vessel in assets
+            .vessels
+            .iter()
+            .filter_map(|handle| vessels.get(handle))
+        {
+            loaded.vessels.insert(vessel.name.clone(), vessel.clone());
+        }
+        for part in assets.parts.iter().filter_map(|handle| parts.get(handle)) {
+            loaded.parts.insert(part.name.clone(), part.clone());
+        }
+        loaded
+    }
+}
+
 #[derive(AssetCollection, Resource)]
-struct VesselAssets {
+pub(crate) struct VesselAssets {
     #[asset(path = "vessels", collection(typed))]
     vessels: Vec<Handle<VesselCfg>>,
 
     #[asset(path = "parts", collection(typed))]
     parts: Vec<Handle<PartCfg>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{DQuat, DVec3};
+
+    use super::*;
+    use crate::vessel::{consumable::Consumable, part_cfg::PartModuleCfgInner};
+
+    fn part_cfg(name: &str) -> PartCfg {
+        let text = std::fs::read_to_string(format!("assets/parts/{name}.part.toml")).unwrap();
+        toml::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn respawned_vessels_keep_their_state() {
+        let vessel_cfg: VesselCfg = toml::from_str(
+            r#"
+name = "test"
+parts = [
+    { id = "core", proto = "dummy" },
+    { id = "shield", proto = "heatshield", position_dm = [0, 0, -51] },
+    { id = "balloon", proto = "envelope", position_dm = [0, 20, 0] },
+    { id = "lost", proto = "cube", position_dm = [0, 0, 55] },
+]
+"#,
+        )
+        .unwrap();
+        let parts = ["dummy", "heatshield", "envelope", "cube"].map(part_cfg);
+        fn module(proto: &PartCfg) -> impl Iterator<Item = PartModuleCfgInner> + '_ {
+            proto.modules.iter().map(|module| module.kind.clone())
+        }
+        let reactor_cfg = module(&parts[0])
+            .find_map(|kind| match kind {
+                PartModuleCfgInner::NuclearReactor(cfg) => Some(cfg),
+                _ => None,
+            })
+            .unwrap();
+        let envelope_cfg = module(&parts[2])
+            .find_map(|kind| match kind {
+                PartModuleCfgInner::GasEnvelope(cfg) => Some(cfg),
+                _ => None,
+            })
+            .unwrap();
+
+        let mut vessel_cfgs = Assets::<VesselCfg>::default();
+        let mut part_cfgs = Assets::<PartCfg>::default();
+        let assets = VesselAssets {
+            vessels: vec![vessel_cfgs.add(vessel_cfg)],
+            parts: parts.map(|part| part_cfgs.add(part)).into(),
+        };
+        let heatshield = assets.parts[1].id();
+        let mut app = App::new();
+        app.add_event::<AssetEvent<VesselCfg>>()
+            .add_event::<AssetEvent<PartCfg>>()
+            .add_event::<SpawnVesselEvent>()
+            .insert_resource(LoadedVessels::collect(&assets, &vessel_cfgs, &part_cfgs))
+            .insert_resource(assets)
+            .insert_resource(vessel_cfgs)
+            .insert_resource(part_cfgs)
+            .insert_resource(HotReload {
+                respawn_vessels: true,
+            })
+            .add_systems(Update, reload_vessels);
+
+        // the "lost" part was destroyed, the shield charred and the envelope partly emptied
+        let world = app.world_mut();
+        let mut tanks = ConsumableTanks::default();
+        tanks.add_tank(Consumable::Hydrogen, 700.0, 1500.0);
+        let vessel = world
+            .spawn((
+                Vessel {
+                    class_name: "test".into(),
+                    vessel_name: "Tester".into(),
+                },
+                VesselControls {
+                    dir_fbw_target: Some(DQuat::from_rotation_x(0.5)),
+                    raw_throttle: 0.7,
+                    raw_steering: DVec3::new(0.1, -0.2, 0.3),
+                    raw_buoyancy: -0.4,
+                    ..default()
+                },
+                PreciseTransform::default(),
+                AngularVelocity(DVec3::Y * 0.01),
+                tanks,
+                CameraFocus,
+            ))
+            .id();
+        let mut part = |id: &str, temperature: f64, strained: bool| {
+            let mut hull = PartHull::new(1e6);
+            hull.strained = strained;
+            world
+                .spawn((
+                    Part {
+                        id: id.into(),
+                        proto: id.into(),
+                        mass: 0.0,
+                    },
+                    PartThermal {
+                        temperature,
+                        max_temperature: 3000.0,
+                        specific_heat: 1000.0,
+                        emissivity: 0.8,
+                        size: DVec3::ONE,
+                    },
+                    hull,
+                    ChildOf(vessel),
+                ))
+                .id()
+        };
+        let core = part("core", 300.0, false);
+        let shield = part("shield", 1350.0, false);
+        let balloon = part("balloon", 250.0, true);
+        let reactor = NuclearReactor {
+            config: reactor_cfg,
+            current_throttle: 0.4,
+            desired_throttle: 0.9,
+        };
+        world.spawn((Module { part: core }, reactor, ChildOf(vessel)));
+        world.spawn((
+            Module { part: shield },
+            Ablator {
+                mass: 120.0,
+                heat_of_ablation: 3e7,
+                char_temperature: 1400.0,
+            },
+            ChildOf(vessel),
+        ));
+        let mut envelope = GasEnvelope::new(envelope_cfg, DVec3::ZERO);
+        envelope.gas_mass = 600.0;
+        world.spawn((Module { part: balloon }, envelope, ChildOf(vessel)));
+
+        world.send_event(AssetEvent::<PartCfg>::Modified { id: heatshield });
+        app.update();
+
+        let world = app.world();
+        assert!(world.get_entity(vessel).is_err());
+        let events = world.resource::<Events<SpawnVesselEvent>>();
+        let respawns = events.iter_current_update_events().collect::<Vec<_>>();
+        let [respawn] = respawns[..] else {
+            panic!("{} respawns", respawns.len());
+        };
+        assert_eq!(respawn.name, "Tester");
+        assert!(respawn.camera_focus);
+        let preserved = respawn.preserved.as_ref().unwrap();
+        assert_eq!(preserved.angular_velocity, DVec3::Y * 0.01);
+        assert_eq!(preserved.dir_fbw_target, Some(DQuat::from_rotation_x(0.5)));
+        assert_eq!(preserved.rot_fbw_target, None);
+        assert_eq!(preserved.throttle, 0.7);
+        assert_eq!(preserved.steering, DVec3::new(0.1, -0.2, 0.3));
+        assert_eq!(preserved.buoyancy, -0.4);
+        assert_eq!(preserved.consumables, vec![(Consumable::Hydrogen, 700.0)]);
+        assert_eq!(preserved.destroyed.iter().collect::<Vec<_>>(), ["lost"]);
+        let kept = |id: &str| &preserved.parts[id];
+        assert_eq!(kept("core").reactors, vec![(0.4, 0.9)]);
+        assert_eq!(kept("shield").temperature, 1350.0);
+        assert_eq!(kept("shield").ablators, vec![120.0]);
+        assert!(kept("balloon").strained);
+        assert_eq!(kept("balloon").envelopes, vec![600.0]);
+    }
+}
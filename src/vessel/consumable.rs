@@ -10,6 +10,11 @@ pub enum Consumable {
     LiquidHydrogen,
     LiquidOxygen,
 
+    /// Gaseous hydrogen, as a lifting gas.
+    Hydrogen,
+    /// Gaseous helium, as a lifting gas.
+    Helium,
+
     Uranium235,
     Plutonium239,
 
@@ -23,12 +28,23 @@ impl Consumable {
             Consumable::Water => 1_000.0,
             Consumable::LiquidHydrogen => 70.85,
             Consumable::LiquidOxygen => 1_141.0,
+            Consumable::Hydrogen => 0.08988,
+            Consumable::Helium => 0.1786,
             Consumable::ElectricJoules => 0.0,
 
             Consumable::Uranium235 => todo!(),
             Consumable::Plutonium239 => todo!(),
         }
     }
+
+    /// Molar mass, in kg/mol, for the consumables kept as a gas.
+    pub fn molar_mass(&self) -> Option<f64> {
+        match self {
+            Consumable::Hydrogen => Some(0.002_016),
+            Consumable::Helium => Some(0.004_003),
+            _ => None,
+        }
+    }
 }
 
 /// Tracks *all* the consumables within a vessel
@@ -57,6 +73,18 @@ impl ConsumableTanks {
         Ok(())
     }
 
+    /// The amount currently held in the tanks for a consumable.
+    pub fn amount(&self, cons: Consumable) -> f64 {
+        self.mapping.get(&cons).map_or(0.0, |slot| slot.0)
+    }
+
+    /// Sets the amount held in the tanks for a consumable, within their capacity. Does nothing without a tank for it.
+    pub fn set_amount(&mut self, cons: Consumable, amt: f64) {
+        if let Some(slot) = self.mapping.get_mut(&cons) {
+            slot.0 = amt.clamp(0.0, slot.1);
+        }
+    }
+
     pub fn consume(&mut self, cons: Consumable, amt: f64) -> f64 {
         if let Some(slot) = self.mapping.get_mut(&cons) {
             slot.0 = (slot.0 - amt).max(0.0);
@@ -7,11 +7,11 @@ use bevy::{
 
 use crate::{
     camera::{CameraFocus, CameraMode, CameraParams, MainCamera},
-    physics::AngularVelocity,
+    physics::{AngularVelocity, aerodynamics::AeroModel},
     precision::PreciseTransform,
     vessel::{
         controls::fbw::{DirectionalFbw, PidDirectionalFbw, PidRotationalFbw, RotationalFbw},
-        modules::{thruster::Thruster, torquer::Torquer},
+        modules::{envelope::GasEnvelope, thruster::Thruster, torquer::Torquer},
     },
 };
 
@@ -28,6 +28,8 @@ pub struct VesselControls {
     /// The "raw" throttle and steering
     pub raw_throttle: f64,
     pub raw_steering: DVec3,
+    /// Gas transfer into (positive) or out of (negative) the gas envelopes
+    pub raw_buoyancy: f64,
 }
 
 impl Default for VesselControls {
@@ -39,6 +41,7 @@ impl Default for VesselControls {
             rot_fbw_impl: Box::new(PidRotationalFbw::new(0.1, 0.1, 0.00, 0.5)),
             raw_throttle: 0.0,
             raw_steering: DVec3::ZERO,
+            raw_buoyancy: 0.0,
         }
     }
 }
@@ -49,7 +52,12 @@ pub fn run_controls(app: &mut App) {
         (
             read_controls,
             fly_by_wire,
-            (control_thrusters, control_torquers),
+            (
+                control_thrusters,
+                control_torquers,
+                control_surfaces,
+                control_envelopes,
+            ),
         )
             .chain(),
     );
@@ -100,6 +108,15 @@ fn read_controls(
     }
     ctrl.raw_throttle = ctrl.raw_throttle.clamp(0.0, 1.0);
 
+    // buoyancy control (R/F) – fill or empty the gas envelopes while held
+    ctrl.raw_buoyancy = if keys.pressed(KeyCode::KeyR) {
+        1.0
+    } else if keys.pressed(KeyCode::KeyF) {
+        -1.0
+    } else {
+        0.0
+    };
+
     if camera_params.mode == CameraMode::WarThunderLike {
         ctrl.dir_fbw_target = Some(camera.rotation);
     } else {
@@ -159,3 +176,26 @@ fn control_torquers(
         }
     }
 }
+
+fn control_surfaces(vessel: Query<(&VesselControls, &mut AeroModel)>, time: Res<Time>) {
+    let dt = time.delta_secs_f64();
+    for (controls, mut aero) in vessel {
+        for (_, wing) in aero.wings.iter_mut() {
+            if let Some(control) = wing.control.as_mut() {
+                control.actuate(controls.raw_steering, dt);
+            }
+        }
+    }
+}
+
+fn control_envelopes(
+    vessel: Query<(&VesselControls, &Children)>,
+    mut envelopes: Query<&mut GasEnvelope>,
+) {
+    for (controls, children) in vessel {
+        let mut envelopes = envelopes.iter_many_mut(children);
+        while let Some(mut envelope) = envelopes.fetch_next() {
+            envelope.fill = controls.raw_buoyancy;
+        }
+    }
+}
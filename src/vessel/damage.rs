@@ -0,0 +1,289 @@
+use bevy::{ecs::entity::EntityHashSet, prelude::*};
+
+use crate::{
+    GameState,
+    physics::{
+        MassProps,
+        aerodynamics::AeroModel,
+        collision::{Collider, ColliderBox},
+    },
+    precision::{PreciseTransform, ToMillimetersExt},
+    vessel::{
+        Part, PartThermal,
+        modules::{Module, envelope::GasEnvelope},
+        spawn::PartAero,
+    },
+};
+
+pub(super) fn run_damage(app: &mut App) {
+    app.add_event::<DestroyPartEvent>().add_systems(
+        FixedPostUpdate,
+        destroy_parts.run_if(in_state(GameState::Game)),
+    );
+}
+
+/// Sent to destroy a part, removing it and its modules from the vessel.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DestroyPartEvent {
+    pub part: Entity,
+    pub cause: PartFailure,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartFailure {
+    /// The part's skin got hotter than it can withstand.
+    Overheat,
+    /// The outside pressure got higher than the part's hull is rated for.
+    Crushed,
+}
+
+/// Removes destroyed parts and their modules, and rebuilds the mass properties, collision geometry and aerodynamic
+/// model of their vessels from the parts left. Each vessel's origin is moved to its new centre of mass, with its parts
+/// and modules kept in place. A vessel with no parts left is removed altogether.
+fn destroy_parts(
+    mut commands: Commands,
+    mut evts: EventReader<DestroyPartEvent>,
+    mut parts: Query<
+        (&Part, &ChildOf, &mut Transform, &PartThermal, &mut PartAero),
+        Without<Module>,
+    >,
+    mut modules: Query<(Entity, &Module, &mut Transform, Option<&mut GasEnvelope>), Without<Part>>,
+    mut vessels: Query<(
+        &Children,
+        &mut PreciseTransform,
+        &mut MassProps,
+        &mut Collider,
+        &mut AeroModel,
+    )>,
+) {
+    let mut destroyed = EntityHashSet::default();
+    let mut damaged = EntityHashSet::default();
+    for evt in evts.read() {
+        if !destroyed.insert(evt.part) {
+            continue;
+        }
+        let Ok((part, child_of, ..)) = parts.get(evt.part) else {
+            continue;
+        };
+        warn!(
+            "part {} ({}) destroyed: {:?}",
+            part.id, part.proto, evt.cause
+        );
+        commands.entity(evt.part).despawn();
+        damaged.insert(child_of.parent());
+    }
+
+    for vessel in damaged {
+        let Ok((children, mut ptf, mut mass, mut collider, mut aero)) = vessels.get_mut(vessel)
+        else {
+            continue;
+        };
+        let remaining = children
+            .iter()
+            .filter(|child| !destroyed.contains(child) && parts.contains(*child))
+            .collect::<Vec<_>>();
+        if remaining.is_empty() {
+            info!("vessel {vessel} lost all its parts");
+            commands.entity(vessel).despawn();
+            continue;
+        }
+
+        let (new_mass, center_of_mass) = MassProps::from_boxes(parts.iter_many(&remaining).map(
+            |(part, _, tf, thermal, _)| {
+                (
+                    part.mass,
+                    tf.translation.as_dvec3(),
+                    tf.rotation.as_dquat(),
+                    thermal.size,
+                )
+            },
+        ));
+        *mass = new_mass;
+        // keep the origin at the centre of mass, and everything else where it was
+        let rotation = ptf.rotation;
+        ptf.translation_mm += (rotation * center_of_mass).to_millimeters();
+        let shift = center_of_mass.as_vec3();
+        let mut part_iter = parts.iter_many_mut(&remaining);
+        while let Some((_, _, mut tf, _, mut part_aero)) = part_iter.fetch_next() {
+            tf.translation -= shift;
+            part_aero.translate(-center_of_mass);
+        }
+        let mut module_iter = modules.iter_many_mut(children);
+        while let Some((module_entity, module, mut tf, envelope)) = module_iter.fetch_next() {
+            if destroyed.contains(&module.part) {
+                commands.entity(module_entity).despawn();
+                continue;
+            }
+            tf.translation -= shift;
+            if let Some(mut envelope) = envelope {
+                envelope.offset -= center_of_mass;
+            }
+        }
+
+        *collider = Collider::new(
+            collider
+                .boxes
+                .iter()
+                .filter(|b| !destroyed.contains(&b.part))
+                .map(|b| ColliderBox {
+                    center: b.center - center_of_mass,
+                    ..*b
+                })
+                .collect(),
+        );
+        // wings are assembled in the order of the parts, and control surfaces keep their deflection
+        let mut old_wings = std::mem::take(&mut aero.wings).into_iter();
+        let mut wings = vec![];
+        for (part_entity, (.., part_aero)) in children
+            .iter()
+            .filter_map(|child| Some((child, parts.get(child).ok()?)))
+        {
+            let old = old_wings.by_ref().take(part_aero.wings.len());
+            if !destroyed.contains(&part_entity) {
+                wings.extend(
+                    part_aero
+                        .wings
+                        .iter()
+                        .zip(old)
+                        .map(|((tf, _), (_, wing))| (*tf, wing)),
+                );
+            }
+        }
+        *aero = PartAero::assemble(parts.iter_many(&remaining).map(|(.., part_aero)| part_aero));
+        aero.wings = wings;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{DMat3, DQuat, DVec3};
+
+    use super::*;
+    use crate::physics::aerodynamics::{MainBodyModel, Wing};
+
+    #[test]
+    fn destroyed_parts_leave_their_vessel() {
+        let mut app = App::new();
+        app.add_event::<DestroyPartEvent>()
+            .add_systems(Update, destroy_parts);
+        let world = app.world_mut();
+
+        // a 2 m cube with a 1 m cube stuck on its +X face, which carries a wing
+        let size_a = DVec3::splat(2.0);
+        let size_b = DVec3::ONE;
+        let (center_a, center_b) = (DVec3::ZERO, DVec3::new(1.5, 0.0, 0.0));
+        let (mass_props, com) = MassProps::from_boxes([
+            (800.0, center_a, DQuat::IDENTITY, size_a),
+            (100.0, center_b, DQuat::IDENTITY, size_b),
+        ]);
+        let (center_a, center_b) = (center_a - com, center_b - com);
+        let vessel = world
+            .spawn((
+                PreciseTransform {
+                    translation_mm: bevy::math::I64Vec3::new(1_000_000, 0, 0),
+                    rotation: DQuat::from_rotation_z(std::f64::consts::FRAC_PI_2),
+                },
+                mass_props,
+            ))
+            .id();
+        let wing = Wing {
+            area: 1.0,
+            span: 1.0,
+            details: Default::default(),
+            control: None,
+        };
+        let mut part = |mass: f64, center: DVec3, size: DVec3, wings: Vec<_>| {
+            world
+                .spawn((
+                    Part {
+                        id: "part".into(),
+                        proto: "part".into(),
+                        mass,
+                    },
+                    PartThermal {
+                        temperature: 290.0,
+                        max_temperature: 1000.0,
+                        specific_heat: 1000.0,
+                        emissivity: 0.8,
+                        size,
+                    },
+                    Transform::from_translation(center.as_vec3()),
+                    PartAero {
+                        aero_box: Some((center, size)),
+                        shapes: vec![],
+                        wings,
+                    },
+                    ChildOf(vessel),
+                ))
+                .id()
+        };
+        let a = part(800.0, center_a, size_a, vec![]);
+        let wing_tf = PreciseTransform {
+            translation_mm: center_b.to_millimeters(),
+            rotation: DQuat::IDENTITY,
+        };
+        let b = part(100.0, center_b, size_b, vec![(wing_tf, wing)]);
+        let module = |part| (Module { part }, Transform::default(), ChildOf(vessel));
+        let module_a = world.spawn(module(a)).id();
+        let module_b = world.spawn(module(b)).id();
+        let collider_box = |part, center, size: DVec3| ColliderBox {
+            part,
+            center,
+            rotation: DQuat::IDENTITY,
+            half_extents: size / 2.0,
+            crash_tolerance: 10.0,
+            restitution: 0.0,
+            friction: 0.5,
+        };
+        let aero = PartAero::assemble([
+            world.get::<PartAero>(a).unwrap(),
+            world.get::<PartAero>(b).unwrap(),
+        ]);
+        world.entity_mut(vessel).insert((
+            Collider::new(vec![
+                collider_box(a, center_a, size_a),
+                collider_box(b, center_b, size_b),
+            ]),
+            aero,
+        ));
+
+        app.world_mut().send_event(DestroyPartEvent {
+            part: b,
+            cause: PartFailure::Overheat,
+        });
+        app.update();
+
+        let world = app.world();
+        assert!(world.get_entity(b).is_err());
+        assert!(world.get_entity(module_b).is_err());
+        let mass = world.get::<MassProps>(vessel).unwrap();
+        assert_eq!(mass.mass, 800.0);
+        let expected = DMat3::from_diagonal(DVec3::splat(800.0 * 8.0 / 12.0));
+        assert!(mass.inertia.abs_diff_eq(expected, 1e-9));
+        // the origin moved onto the remaining cube, turned by the vessel's rotation
+        let ptf = world.get::<PreciseTransform>(vessel).unwrap();
+        assert_eq!(ptf.translation_mm.x, 1_000_000);
+        assert_eq!(ptf.translation_mm.y, -(com.x * 1000.0).round() as i64);
+        assert!(world.get::<Transform>(a).unwrap().translation.length() < 1e-6);
+        assert!(
+            (world.get::<Transform>(module_a).unwrap().translation - com.as_vec3()).length() < 1e-6
+        );
+        let collider = world.get::<Collider>(vessel).unwrap();
+        assert_eq!(collider.boxes.len(), 1);
+        assert!(collider.boxes[0].center.length() < 1e-6);
+        let aero = world.get::<AeroModel>(vessel).unwrap();
+        assert!(aero.wings.is_empty());
+        let MainBodyModel::Composite(shapes) = &aero.main else {
+            panic!("{:?} isn't composite", aero.main);
+        };
+        assert_eq!(shapes.len(), 1);
+
+        // losing the last part removes the vessel
+        app.world_mut().send_event(DestroyPartEvent {
+            part: a,
+            cause: PartFailure::Crushed,
+        });
+        app.update();
+        assert!(app.world().get_entity(vessel).is_err());
+    }
+}
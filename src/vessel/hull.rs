@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState,
+    physics::aerodynamics::AeroEnv,
+    vessel::{DestroyPartEvent, Part, PartFailure, Vessel},
+};
+
+/// Fraction of its rating at which a part's hull starts to warn of the outside pressure.
+const WARNING_FRACTION: f64 = 0.8;
+
+pub(super) fn run_hull(app: &mut App) {
+    app.add_systems(FixedUpdate, crush_parts.run_if(in_state(GameState::Game)));
+}
+
+/// The pressure rating of a part's hull.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PartHull {
+    /// External pressure (Pa) beyond which the part is crushed.
+    pub max_pressure: f64,
+    /// Whether the outside pressure has come close to the rating.
+    pub strained: bool,
+}
+
+impl PartHull {
+    pub fn new(max_pressure: f64) -> Self {
+        Self {
+            max_pressure,
+            strained: false,
+        }
+    }
+}
+
+/// Compares the outside pressure to each part's rating, warning as it gets close and crushing parts beyond it.
+fn crush_parts(
+    vessels: Query<(&Vessel, &AeroEnv, &Children)>,
+    mut parts: Query<(Entity, &Part, &mut PartHull)>,
+    mut destroy: EventWriter<DestroyPartEvent>,
+) {
+    for (vessel, aero, children) in vessels.iter() {
+        let mut parts = parts.iter_many_mut(children);
+        while let Some((part_entity, part, mut hull)) = parts.fetch_next() {
+            let load = aero.pressure / hull.max_pressure;
+            if load > 1.0 {
+                destroy.write(DestroyPartEvent {
+                    part: part_entity,
+                    cause: PartFailure::Crushed,
+                });
+            } else if load > WARNING_FRACTION && !hull.strained {
+                hull.strained = true;
+                warn!(
+                    "{}: hull of part {} ({}) at {:.0}% of its pressure rating",
+                    vessel.vessel_name,
+                    part.id,
+                    part.proto,
+                    load * 100.0
+                );
+            } else if load < WARNING_FRACTION * 0.9 {
+                // some slack, so the warning doesn't repeat while hovering around the threshold
+                hull.strained = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warns_then_crushes() {
+        let mut app = App::new();
+        app.add_event::<DestroyPartEvent>()
+            .add_systems(Update, crush_parts);
+        let world = app.world_mut();
+        let vessel = world
+            .spawn((
+                Vessel {
+                    class_name: "test".into(),
+                    vessel_name: "test".into(),
+                },
+                AeroEnv::default(),
+            ))
+            .id();
+        let part = world
+            .spawn((
+                Part {
+                    id: "part".into(),
+                    proto: "part".into(),
+                    mass: 100.0,
+                },
+                PartHull::new(1e6),
+                ChildOf(vessel),
+            ))
+            .id();
+
+        // (outside pressure, whether the hull is strained afterwards, whether it's crushed)
+        let steps = [
+            (5e5, false, false),
+            (8.5e5, true, false),
+            // hovering just below the warning keeps the hull strained
+            (7.5e5, true, false),
+            (8.5e5, true, false),
+            (7e5, false, false),
+            (1.1e6, false, true),
+        ];
+        for (pressure, strained, crushed) in steps {
+            app.world_mut().get_mut::<AeroEnv>(vessel).unwrap().pressure = pressure;
+            app.update();
+            let world = app.world();
+            assert_eq!(
+                world.get::<PartHull>(part).unwrap().strained,
+                strained,
+                "at {pressure} Pa"
+            );
+            let events = world.resource::<Events<DestroyPartEvent>>();
+            let destroyed = events
+                .iter_current_update_events()
+                .map(|evt| (evt.part, evt.cause))
+                .collect::<Vec<_>>();
+            let expected = if crushed {
+                vec![(part, PartFailure::Crushed)]
+            } else {
+                vec![]
+            };
+            assert_eq!(destroyed, expected, "at {pressure} Pa");
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use bevy::{math::DVec3, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    orrery::{Celestial, Orrery},
+    physics::{AccumulatedForce, AccumulatedTorque, WithinSoi, aerodynamics::AeroEnv},
+    precision::{PreciseTransform, ToMetersExt},
+    vessel::consumable::{Consumable, ConsumableTanks},
+};
+
+/// Universal gas constant, in J mol⁻¹ K⁻¹.
+const R_UNIV: f64 = 8.314_462_618;
+
+pub fn start_envelopes(app: &mut App) {
+    app.add_systems(
+        FixedUpdate,
+        (fill_envelopes, apply_buoyancy)
+            .chain()
+            .run_if(in_state(GameState::Game)),
+    );
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GasEnvelopeCfg {
+    /// Volume of the envelope when fully inflated, in m³.
+    pub volume: f64,
+    /// The lifting gas, drawn from and returned to the vessel's tanks. It must be a gas, with a molar mass.
+    #[serde(deserialize_with = "lift_gas")]
+    pub lift_gas: Consumable,
+    /// Largest fraction of the envelope that the ballonet can fill with air, to keep its shape as the gas
+    /// contracts.
+    pub ballonet_fraction: f64,
+    /// Rate at which gas is moved between the envelope and the tanks, in kg/s.
+    pub fill_rate: f64,
+    /// Mass of lifting gas in the envelope at spawn, in kg.
+    #[serde(default)]
+    pub gas_mass: f64,
+}
+
+fn lift_gas<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Consumable, D::Error> {
+    let gas = Consumable::deserialize(deserializer)?;
+    if gas.molar_mass().is_none() {
+        return Err(serde::de::Error::custom(format!(
+            "{gas:?} can't be used as a lifting gas"
+        )));
+    }
+    Ok(gas)
+}
+
+impl GasEnvelopeCfg {
+    /// Molar mass of the lifting gas, in kg mol⁻¹.
+    pub fn molar_mass(&self) -> f64 {
+        self.lift_gas
+            .molar_mass()
+            .expect("lifting gases are checked when loaded")
+    }
+}
+
+/// An envelope of lifting gas, at the pressure and temperature of the surrounding air.
+#[derive(Component, Debug)]
+pub struct GasEnvelope {
+    pub cfg: GasEnvelopeCfg,
+    /// Centre of the envelope (m), relative to the vessel's centre of mass.
+    pub offset: DVec3,
+    /// Mass of lifting gas in the envelope, in kg.
+    pub gas_mass: f64,
+    /// Commanded gas transfer, from -1 (pump back into tanks) to 1 (fill from tanks).
+    pub fill: f64,
+    /// Volume of the ballonet, in m³.
+    pub ballonet_volume: f64,
+    /// Net lift (N), after the weight of the gas.
+    pub lift: f64,
+}
+
+impl GasEnvelope {
+    pub fn new(cfg: GasEnvelopeCfg, offset: DVec3) -> Self {
+        Self {
+            cfg,
+            offset,
+            gas_mass: cfg.gas_mass,
+            fill: 0.0,
+            ballonet_volume: 0.0,
+            lift: 0.0,
+        }
+    }
+
+    /// Radius (m) of a sphere with the envelope's volume.
+    pub fn radius(&self) -> f64 {
+        (3.0 * self.cfg.volume / (4.0 * std::f64::consts::PI)).cbrt()
+    }
+
+    /// Volume (m³) the gas takes up at a given ambient pressure (Pa) and temperature (K).
+    fn gas_volume(&self, pressure: f64, temperature: f64) -> f64 {
+        if pressure <= 0.0 {
+            return f64::INFINITY;
+        }
+        self.gas_mass * R_UNIV * temperature / (self.cfg.molar_mass() * pressure)
+    }
+}
+
+/// Moves gas between the tanks and the envelopes, and vents gas that would overinflate an envelope.
+fn fill_envelopes(
+    envelopes: Query<(&mut GasEnvelope, &ChildOf)>,
+    mut vessels: Query<(&mut ConsumableTanks, &AeroEnv)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs_f64();
+    for (mut envelope, child_of) in envelopes {
+        let Ok((mut tanks, aero)) = vessels.get_mut(child_of.parent()) else {
+            continue;
+        };
+        let gas = envelope.cfg.lift_gas;
+        let transfer = envelope.fill.clamp(-1.0, 1.0) * envelope.cfg.fill_rate * dt;
+        if transfer > 0.0 {
+            let available = tanks.amount(gas);
+            let taken = transfer.min(available);
+            tanks.consume(gas, taken);
+            envelope.gas_mass += taken;
+        } else if transfer < 0.0 {
+            let returned = (-transfer).min(envelope.gas_mass);
+            if tanks.produce(gas, returned).is_ok() {
+                envelope.gas_mass -= returned;
+            }
+        }
+
+        // the envelope can't hold more than its volume; the excess escapes through the relief valves
+        let volume = envelope.gas_volume(aero.pressure, aero.temperature);
+        if volume > envelope.cfg.volume {
+            envelope.gas_mass *= envelope.cfg.volume / volume;
+        }
+        let volume = envelope
+            .gas_volume(aero.pressure, aero.temperature)
+            .min(envelope.cfg.volume);
+        envelope.ballonet_volume = (envelope.cfg.volume - volume)
+            .min(envelope.cfg.volume * envelope.cfg.ballonet_fraction);
+    }
+}
+
+/// Applies the buoyant force of the displaced air, net of the weight of the lifting gas.
+fn apply_buoyancy(
+    orrery: Res<Orrery>,
+    envelopes: Query<(&mut GasEnvelope, &ChildOf)>,
+    mut vessels: Query<(
+        &PreciseTransform,
+        &AeroEnv,
+        &WithinSoi,
+        &mut AccumulatedForce,
+        &mut AccumulatedTorque,
+    )>,
+    planets: Query<(&Celestial, &PreciseTransform)>,
+) {
+    for (mut envelope, child_of) in envelopes {
+        let Ok((ptf, aero, soi, mut force, mut torque)) = vessels.get_mut(child_of.parent()) else {
+            continue;
+        };
+        let Ok((planet, planet_ptf)) = planets.get(soi.0) else {
+            continue;
+        };
+        let to_vessel = (ptf.translation_mm - planet_ptf.translation_mm).to_meters_64();
+        let Some(gravity) = orrery.gravity_at(&planet.0, to_vessel.length()) else {
+            continue;
+        };
+
+        // air in the ballonet weighs as much as the air it displaces, so only the gas itself lifts
+        let volume = envelope
+            .gas_volume(aero.pressure, aero.temperature)
+            .min(envelope.cfg.volume);
+        envelope.lift = gravity * (aero.density * volume - envelope.gas_mass);
+
+        let lift = to_vessel.normalize_or_zero() * envelope.lift;
+        force.0 += lift;
+        torque.0 += ptf.rotation.mul_vec3(envelope.offset).cross(lift);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::math::I64Vec3;
+    use hifitime::Epoch;
+
+    use super::*;
+
+    const AIR_MOLAR_MASS: f64 = 0.028_964_4;
+
+    fn planet() -> Orrery {
+        let yaml = r#"
+name: "planet"
+bodies:
+  - name: "Planet"
+    class: planet
+    mass: "1 massEarth"
+    radius: 6.4e6
+"#;
+        Orrery::init(
+            serde_yml::from_str(yaml).unwrap(),
+            Epoch::from_tai_seconds(0.0),
+        )
+        .unwrap()
+    }
+
+    /// A vessel on the planet's +Y axis, carrying a hydrogen envelope offset along its +X axis, in air at the given
+    /// pressure (Pa) and temperature (K).
+    fn setup(gas_mass: f64, pressure: f64, temperature: f64) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.insert_resource(planet())
+            .insert_resource(Time::<()>::default())
+            .add_systems(Update, (fill_envelopes, apply_buoyancy).chain());
+        let world = app.world_mut();
+        let planet = world
+            .spawn((Celestial("Planet".into()), PreciseTransform::default()))
+            .id();
+        let mut tanks = ConsumableTanks::default();
+        tanks.add_tank(Consumable::Hydrogen, 500.0, 1500.0);
+        let vessel = world
+            .spawn((
+                PreciseTransform {
+                    translation_mm: I64Vec3::new(0, 6_400_000_000, 0),
+                    ..Default::default()
+                },
+                AeroEnv {
+                    pressure,
+                    temperature,
+                    density: pressure * AIR_MOLAR_MASS / (R_UNIV * temperature),
+                    ..Default::default()
+                },
+                WithinSoi(planet),
+                AccumulatedForce::default(),
+                AccumulatedTorque::default(),
+                tanks,
+            ))
+            .id();
+        let cfg = GasEnvelopeCfg {
+            volume: 20_000.0,
+            lift_gas: Consumable::Hydrogen,
+            ballonet_fraction: 0.3,
+            fill_rate: 20.0,
+            gas_mass,
+        };
+        let envelope = world
+            .spawn((GasEnvelope::new(cfg, DVec3::X * 2.0), ChildOf(vessel)))
+            .id();
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        (app, vessel, envelope)
+    }
+
+    #[test]
+    fn lift_gas_must_be_a_gas() {
+        let cfg = |gas: &str| {
+            toml::from_str::<GasEnvelopeCfg>(&format!(
+                "volume = 1.0\nlift_gas = \"{gas}\"\nballonet_fraction = 0.0\nfill_rate = 1.0"
+            ))
+        };
+        assert_eq!(cfg("Helium").unwrap().molar_mass(), 0.004_003);
+        assert!(cfg("Water").is_err());
+    }
+
+    #[test]
+    fn neutral_buoyancy_at_sea_level() {
+        let (mut app, vessel, envelope) = setup(500.0, 101_325.0, 288.15);
+        app.update();
+
+        // each kilogram of hydrogen displaces M_air / M_H2 kilograms of air at the same pressure and temperature
+        let g = planet().gravity_at("Planet", 6.4e6).unwrap();
+        let floated = 500.0 * (AIR_MOLAR_MASS / 0.002_016 - 1.0);
+        let world = app.world();
+        let lift = world.get::<GasEnvelope>(envelope).unwrap().lift;
+        assert!((lift / (floated * g) - 1.0).abs() < 1e-12, "{lift}");
+        // so a vessel of that mass is neutrally buoyant
+        let force = world.get::<AccumulatedForce>(vessel).unwrap().0;
+        assert!(force.abs_diff_eq(DVec3::Y * floated * g, 1e-6));
+        let torque = world.get::<AccumulatedTorque>(vessel).unwrap().0;
+        assert!(torque.abs_diff_eq(DVec3::Z * 2.0 * floated * g, 1e-6));
+        // the gas fills less than a third of the envelope, and the ballonet makes up for what it can
+        let envelope = world.get::<GasEnvelope>(envelope).unwrap();
+        assert_eq!(envelope.gas_mass, 500.0);
+        assert_eq!(envelope.ballonet_volume, 6_000.0);
+    }
+
+    #[test]
+    fn fills_from_and_returns_to_the_tanks() {
+        let (mut app, vessel, envelope) = setup(100.0, 101_325.0, 288.15);
+        app.world_mut()
+            .get_mut::<GasEnvelope>(envelope)
+            .unwrap()
+            .fill = 1.0;
+        app.update();
+        let gas = |app: &App| {
+            let world = app.world();
+            (
+                world.get::<GasEnvelope>(envelope).unwrap().gas_mass,
+                world
+                    .get::<ConsumableTanks>(vessel)
+                    .unwrap()
+                    .amount(Consumable::Hydrogen),
+            )
+        };
+        assert_eq!(gas(&app), (120.0, 480.0));
+
+        app.world_mut()
+            .get_mut::<GasEnvelope>(envelope)
+            .unwrap()
+            .fill = -0.5;
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        app.update();
+        assert_eq!(gas(&app), (110.0, 490.0));
+    }
+
+    #[test]
+    fn vents_rather_than_overinflate() {
+        // at altitude, a tonne of hydrogen would take up about five times the envelope
+        let (mut app, _, envelope) = setup(1000.0, 10_000.0, 250.0);
+        app.update();
+        let world = app.world();
+        let envelope = world.get::<GasEnvelope>(envelope).unwrap();
+        let full = 20_000.0 * 0.002_016 * 10_000.0 / (R_UNIV * 250.0);
+        assert!((envelope.gas_mass - full).abs() < 1e-9);
+        assert!(envelope.ballonet_volume.abs() < 1e-9);
+        assert!(envelope.lift > 0.0);
+    }
+}
@@ -1,14 +1,23 @@
 use bevy::prelude::*;
 
+use crate::precision::PreciseGlobalTransform;
+
+pub mod envelope;
 pub mod reactor;
 pub mod thruster;
 pub mod torquer;
 
+/// A module of a part, spawned as a child of the vessel entity at its place on the part.
 #[derive(Component)]
-pub struct Module;
+#[require(Transform, Visibility, PreciseGlobalTransform)]
+pub struct Module {
+    /// The part this module belongs to.
+    pub part: Entity,
+}
 
 pub fn start_modules(app: &mut App) {
     app.add_plugins((
+        envelope::start_envelopes,
         reactor::start_reactors,
         thruster::start_thrusters,
         torquer::start_torquers,
@@ -4,7 +4,7 @@ use bevy::{math::DVec3, prelude::*};
 
 use crate::{
     physics::{AccumulatedForce, AccumulatedTorque, aerodynamics::AeroEnv},
-    precision::PreciseTransform,
+    precision::PreciseGlobalTransform,
     vessel::consumable::{Consumable, ConsumableTanks},
 };
 
@@ -33,36 +33,37 @@ fn magic_thrusters(mut query: Query<(&mut Thruster, &MagicThruster)>) {
 }
 
 fn apply_thrusters(
-    thrusters: Query<(&Thruster, &ChildOf)>,
+    thrusters: Query<(&Thruster, &PreciseGlobalTransform, &ChildOf)>,
     mut vessels: Query<(
-        &PreciseTransform,
+        &PreciseGlobalTransform,
         &mut AccumulatedForce,
         &mut AccumulatedTorque,
     )>,
 ) {
-    for (thruster, child_of) in thrusters {
-        if let Ok((ptf, mut force, mut torque)) = vessels.get_mut(child_of.parent()) {
+    for (thruster, thruster_tf, child_of) in thrusters {
+        if let Ok((vessel_tf, mut force, mut torque)) = vessels.get_mut(child_of.parent()) {
             // Calculate the thrust force vector
-            let thrust_force =
-                ptf.rotation.mul_vec3(thruster.direction).normalize() * thruster.current_thrust;
+            let thrust_force = thruster_tf
+                .transform_direction(thruster.direction)
+                .normalize()
+                * thruster.current_thrust;
 
             // Add the force to accumulated force
             force.0 += thrust_force;
 
-            // Calculate and add torque (cross product of offset and force)
-            let world_offset = ptf.rotation.mul_vec3(thruster.offset);
-            torque.0 += world_offset.cross(thrust_force);
+            // Calculate and add torque (cross product of lever arm and force)
+            torque.0 += thruster_tf.offset_from(vessel_tf).cross(thrust_force);
         }
     }
 }
 
 #[derive(Component, Default)]
 #[require(Transform)]
-/// A thruster.
+/// A thruster, placed by the `Transform` of its module.
 pub struct Thruster {
     pub throttle: f64,
     pub current_thrust: f64,
-    pub offset: DVec3,
+    /// Direction of the thrust, in the module's axes.
     pub direction: DVec3,
 }
 
@@ -74,6 +75,7 @@ pub struct MagicThruster {
 
 #[derive(Component)]
 #[require(MeshMaterial3d<StandardMaterial>, Mesh3d)]
+/// The flame of the thruster it's a child of.
 pub struct SimpleThrusterFlame {
     pub radius: f32,
     pub length_per_newton: f32,
@@ -103,22 +105,28 @@ fn load_flame_model(
 
 fn render_flames(
     model: Res<FlameModel>,
-    thrusters: Query<(
-        &Thruster,
+    thrusters: Query<&Thruster>,
+    flames: Query<(
         &SimpleThrusterFlame,
+        &ChildOf,
         &mut Mesh3d,
         &mut MeshMaterial3d<StandardMaterial>,
         &mut Transform,
     )>,
 ) {
-    for (thruster, flame, mut mesh, mut material, mut transform) in thrusters {
+    for (flame, child_of, mut mesh, mut material, mut transform) in flames {
+        let Ok(thruster) = thrusters.get(child_of.parent()) else {
+            continue;
+        };
         let flame_length = thruster.current_thrust as f32 * flame.length_per_newton;
         if flame_length == 0.0 {
             *mesh = Default::default();
             *material = Default::default()
         } else {
-            transform.translation = thruster.offset.as_vec3();
-            transform.translation.z += flame_length / 2.0;
+            // the flame trails behind the thrust
+            let exhaust = -thruster.direction.as_vec3().normalize_or(Vec3::NEG_Z);
+            transform.rotation = Quat::from_rotation_arc(Vec3::Z, exhaust);
+            transform.translation = exhaust * flame_length / 2.0;
             *mesh = model.mesh.clone();
             *material = model.material.clone();
             transform.scale.z = flame_length;
@@ -1,6 +1,6 @@
 use bevy::{math::DVec3, prelude::*};
 
-use crate::{physics::AccumulatedTorque, precision::PreciseTransform};
+use crate::{physics::AccumulatedTorque, precision::PreciseGlobalTransform};
 
 pub fn start_torquers(app: &mut App) {
     app.add_systems(FixedUpdate, (apply_torquers, magic_torquers));
@@ -11,19 +11,18 @@ pub fn start_torquers(app: &mut App) {
 pub struct Torquer {
     /// A vector indicating throttle position in all three axes.
     pub throttle: DVec3,
-    /// Actual torque produced
+    /// Actual torque produced, in the vessel's axes
     pub torque: DVec3,
-    /// Offset of the torquer
-    pub offset: DVec3,
 }
 
 fn apply_torquers(
     torquers: Query<(&Torquer, &ChildOf)>,
-    mut vessels: Query<(&PreciseTransform, &mut AccumulatedTorque)>,
+    mut vessels: Query<(&PreciseGlobalTransform, &mut AccumulatedTorque)>,
 ) {
     for (torquer, child_of) in torquers {
-        if let Ok((ptf, mut torque)) = vessels.get_mut(child_of.0) {
-            torque.0 += ptf.rotation.mul_vec3(torquer.torque);
+        if let Ok((vessel_tf, mut torque)) = vessels.get_mut(child_of.0) {
+            // a pure torque doesn't depend on where it's applied
+            torque.0 += vessel_tf.transform_direction(torquer.torque);
         }
     }
 }
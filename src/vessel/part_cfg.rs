@@ -3,7 +3,9 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 
+use crate::physics::aerodynamics::Wing;
 use crate::vessel::consumable::Consumable;
+use crate::vessel::modules::envelope::GasEnvelopeCfg;
 use crate::vessel::modules::reactor::NuclearReactorCfg;
 
 #[derive(Asset, TypePath, Clone, Debug, Serialize, Deserialize)]
@@ -18,10 +20,98 @@ pub struct PartCfg {
 
     pub empty_mass: f64,
 
+    #[serde(default)]
+    pub aero_shape: PartAeroShape,
+
+    #[serde(default)]
+    pub collision: PartCollisionCfg,
+
+    #[serde(default)]
+    pub thermal: PartThermalCfg,
+
+    /// External pressure (Pa) beyond which the part's hull is crushed.
+    #[serde(default = "default_max_pressure")]
+    pub max_pressure: f64,
+
     #[serde(default)]
     pub modules: Vec<PartModuleCfg>,
 }
 
+fn default_max_pressure() -> f64 {
+    1.0e7
+}
+
+/// The shape a part presents to the airflow, sized by its `dimensions_dm`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PartAeroShape {
+    #[default]
+    Box,
+    /// A cylinder along the part's Z axis.
+    Cylinder,
+    /// A nose cone along the part's Z axis, pointing towards -Z.
+    Nosecone,
+    /// No drag of its own, e.g. for parts enclosed by others.
+    None,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartCollisionCfg {
+    /// Impact speed (m/s) along the contact normal beyond which the part crashes.
+    pub crash_tolerance: f64,
+    /// Coefficient of restitution.
+    pub restitution: f64,
+    /// Coefficient of friction.
+    pub friction: f64,
+}
+
+impl Default for PartCollisionCfg {
+    fn default() -> Self {
+        Self {
+            crash_tolerance: 10.0,
+            restitution: 0.2,
+            friction: 0.6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartThermalCfg {
+    /// Specific heat capacity of the part's structure, in J kg⁻¹ K⁻¹.
+    pub specific_heat: f64,
+    /// Skin temperature (K) beyond which the part is destroyed.
+    pub max_temperature: f64,
+    /// Emissivity of the part's skin.
+    pub emissivity: f64,
+}
+
+impl Default for PartThermalCfg {
+    fn default() -> Self {
+        Self {
+            specific_heat: 900.0,
+            max_temperature: 1_200.0,
+            emissivity: 0.8,
+        }
+    }
+}
+
+impl PartCfg {
+    /// Mass of the part as spawned, including any ablator.
+    pub fn mass(&self) -> f64 {
+        self.empty_mass
+            + self
+                .modules
+                .iter()
+                .map(|module| match module.kind {
+                    PartModuleCfgInner::Ablator { mass, .. } => mass,
+                    _ => 0.0,
+                })
+                .sum::<f64>()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PartModuleCfg {
     #[serde(default)]
@@ -53,6 +143,19 @@ pub enum PartModuleCfgInner {
         fraction: f64,
     },
     NuclearReactor(NuclearReactorCfg),
+    /// A lifting surface, with span along the part's X axis and chord along its Z axis.
+    Wing(Wing),
+    /// Ablative material that absorbs heat as it burns away.
+    Ablator {
+        /// Mass of ablator, in kg.
+        mass: f64,
+        /// Heat absorbed per kilogram ablated, in J/kg.
+        heat_of_ablation: f64,
+        /// Skin temperature (K) at which the ablator starts to burn away.
+        char_temperature: f64,
+    },
+    /// An envelope of lifting gas, drawn from the vessel's tanks.
+    GasEnvelope(GasEnvelopeCfg),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -60,3 +163,37 @@ pub enum PartModuleCfgInner {
 pub enum ThrusterFlameCfg {
     Simple { radius: f32, max_length: f32 },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, Result};
+
+    fn load(path: impl AsRef<std::path::Path>) -> Result<PartCfg> {
+        let path = path.as_ref();
+        toml::from_str(&std::fs::read_to_string(path)?)
+            .with_context(|| format!("loading {}", path.display()))
+    }
+
+    #[test]
+    fn every_asset_part_loads() -> Result<()> {
+        for entry in std::fs::read_dir("assets/parts")? {
+            load(entry?.path())?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn wings_are_part_modules() -> Result<()> {
+        let wing = load("assets/parts/wing.part.toml")?;
+        let [module] = &wing.modules[..] else {
+            panic!("{:?} should have a single module", wing.modules);
+        };
+        let PartModuleCfgInner::Wing(wing) = &module.kind else {
+            panic!("{:?} isn't a wing", module.kind);
+        };
+        assert_eq!((wing.area, wing.span), (9.0, 6.0));
+        assert!(wing.control.is_none());
+        Ok(())
+    }
+}
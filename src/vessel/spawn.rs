@@ -1,48 +1,91 @@
+mod initial_state;
+
 use bevy::{
-    math::{DMat3, DQuat, DVec3},
+    math::{DQuat, DVec3},
     prelude::*,
 };
 use smol_str::SmolStr;
-use std::f32::consts::{FRAC_PI_2, PI};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    f32::consts::{FRAC_PI_2, PI},
+};
 
 use crate::{
     GameState,
     camera::CameraFocus,
     orrery::Orrery,
-    physics::{MassProps, aerodynamics::AeroModel, sim_time},
-    precision::{PreciseTransform, ToMetersExt, ToMillimetersExt},
+    physics::{
+        AngularVelocity, MassProps, SimTime, Velocity,
+        aerodynamics::{AeroModel, MainBodyModel, Wing},
+        collision::{Collider, ColliderBox},
+    },
+    precision::{PreciseTransform, ToMillimetersExt},
     vessel::{
-        LoadedVessels, Vessel, VesselControls,
-        consumable::ConsumableTanks,
-        load_vessels,
+        Ablator, LoadedVessels, Part, PartHull, PartThermal, Vessel, VesselControls,
+        consumable::{Consumable, ConsumableTanks},
         modules::{
             Module,
+            envelope::GasEnvelope,
             reactor::NuclearReactor,
             thruster::{ElectricFan, MagicThruster, SimpleThrusterFlame, Thruster},
-            torquer::{MagicTorquer, Torquer},
+            torquer::MagicTorquer,
         },
-        part_cfg::{PartModuleCfgInner, ThrusterFlameCfg},
+        part_cfg::{PartAeroShape, PartModuleCfgInner, ThrusterFlameCfg},
         vessel_cfg::{Face, QuarterTurn, VesselCfg},
     },
 };
 
+pub use initial_state::InitialState;
+
 #[derive(Event, Clone)]
 pub struct SpawnVesselEvent {
     pub cfg: VesselCfg,
     pub name: SmolStr,
-    pub location: PreciseTransform,
+    pub initial: InitialState,
     pub camera_focus: bool,
+    /// State carried over from a vessel this one replaces, if any.
+    pub preserved: Option<PreservedState>,
+}
+
+/// The state of a vessel that survives respawning it, e.g. after its configuration changed. Only the fly-by-wire
+/// controllers and the deflection of control surfaces start afresh.
+#[derive(Clone, Debug, Default)]
+pub struct PreservedState {
+    pub angular_velocity: DVec3,
+    pub dir_fbw_target: Option<DQuat>,
+    pub rot_fbw_target: Option<DVec3>,
+    pub throttle: f64,
+    pub steering: DVec3,
+    pub buoyancy: f64,
+    /// Amount of each consumable, kept within the capacity of the new tanks.
+    pub consumables: Vec<(Consumable, f64)>,
+    /// State of the parts left, by id.
+    pub parts: BTreeMap<SmolStr, PreservedPart>,
+    /// Ids of the parts that were destroyed, which aren't spawned again.
+    pub destroyed: BTreeSet<SmolStr>,
+}
+
+/// The state of a part that survives respawning its vessel. Modules are matched by their order within the part.
+#[derive(Clone, Debug, Default)]
+pub struct PreservedPart {
+    /// Skin temperature, in K.
+    pub temperature: f64,
+    pub strained: bool,
+    /// Remaining mass (kg) of each ablator.
+    pub ablators: Vec<f64>,
+    /// Mass (kg) of lifting gas in each envelope.
+    pub envelopes: Vec<f64>,
+    /// Current and desired throttle of each reactor.
+    pub reactors: Vec<(f64, f64)>,
 }
 
 pub fn run_spawn(app: &mut App) {
-    app.add_event::<SpawnVesselEvent>()
-        .add_systems(OnEnter(GameState::Game), spawn_vessels.after(load_vessels))
-        .add_systems(
-            FixedUpdate,
-            handle_spawn_vessel
-                .run_if(in_state(GameState::Game))
-                .run_if(resource_exists::<LoadedVessels>),
-        );
+    app.add_event::<SpawnVesselEvent>().add_systems(
+        FixedUpdate,
+        handle_spawn_vessel
+            .run_if(in_state(GameState::Game))
+            .run_if(resource_exists::<LoadedVessels>),
+    );
 }
 
 fn handle_spawn_vessel(
@@ -50,20 +93,35 @@ fn handle_spawn_vessel(
     mut evts: EventReader<SpawnVesselEvent>,
     vessels: Res<LoadedVessels>,
     loader: Res<AssetServer>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<StandardMaterial>>),
     already_focused: Query<Entity, With<CameraFocus>>,
+    (orrery, time): (Res<Orrery>, SimTime),
 ) {
     let gray = MeshMaterial3d(materials.add(Color::srgb_u8(128, 128, 128)));
+    let epoch = time.epoch();
     for spawn_evt in evts.read() {
+        let Some((location, velocity)) = spawn_evt.initial.resolve(&orrery, epoch) else {
+            warn!(
+                "cannot place vessel {} at {:?}",
+                spawn_evt.name, spawn_evt.initial
+            );
+            continue;
+        };
         let vessel_cfg = &spawn_evt.cfg;
+        let preserved = spawn_evt.preserved.as_ref();
         let parts = vessel_cfg
             .parts
             .iter()
+            .filter(|part| {
+                !preserved.is_some_and(|preserved| preserved.destroyed.contains(&part.id))
+            })
             .map(|part| (part, vessels.parts.get(&part.proto).unwrap()))
             .collect::<Vec<_>>();
 
         let mut consumable_tanks = ConsumableTanks::default();
+        let mut collider_boxes = Vec::with_capacity(parts.len());
+        let mut mass_boxes = Vec::with_capacity(parts.len());
+        let mut part_aeros = Vec::with_capacity(parts.len());
 
         // first, we compute the COG for the whole ship
         let center_of_gravity = {
@@ -71,30 +129,23 @@ fn handle_spawn_vessel(
             let mut divisor = 0.0;
             for (part, proto) in parts.iter() {
                 let part_cog = dm_to_meters(part.position_dm);
-                accum += part_cog * proto.empty_mass as f32;
-                divisor += proto.empty_mass as f32;
+                accum += part_cog * proto.mass() as f32;
+                divisor += proto.mass() as f32;
             }
             accum /= divisor;
             accum
         };
 
-        let total_mass = parts.iter().map(|p| p.1.empty_mass).sum::<f64>();
-
         let vessel = commands
             .spawn((
                 Vessel {
                     class_name: vessel_cfg.name.clone(),
                     vessel_name: spawn_evt.name.clone(),
                 },
-                MassProps {
-                    mass: total_mass,
-                    inertia: DMat3::IDENTITY,
-                    inertia_inv: DMat3::IDENTITY,
-                },
-                spawn_evt.location,
+                location,
+                Velocity(velocity),
                 VesselControls::default(),
                 Visibility::default(),
-                AeroModel::default(),
             ))
             .id();
 
@@ -134,7 +185,87 @@ fn handle_spawn_vessel(
                 rotation,
                 ..default()
             };
-            let mut ent = commands.spawn((ChildOf(vessel), child_tf));
+            let size = proto.dimensions_dm.as_dvec3() / 10.0;
+            let kept = preserved.and_then(|preserved| preserved.parts.get(&part.id));
+            let mut ablators = kept
+                .into_iter()
+                .flat_map(|kept| kept.ablators.iter().copied());
+            let mut envelopes = kept
+                .into_iter()
+                .flat_map(|kept| kept.envelopes.iter().copied());
+            let mut reactors = kept
+                .into_iter()
+                .flat_map(|kept| kept.reactors.iter().copied());
+            // whatever the ablators lost is gone from the part
+            let ablated = proto
+                .modules
+                .iter()
+                .filter_map(|module| match module.kind {
+                    PartModuleCfgInner::Ablator { mass, .. } => Some(mass),
+                    _ => None,
+                })
+                .zip(kept.into_iter().flat_map(|kept| kept.ablators.iter()))
+                .map(|(initial, left)| initial - left)
+                .sum::<f64>();
+            let mass = proto.mass() - ablated;
+            let mut hull = PartHull::new(proto.max_pressure);
+            hull.strained = kept.is_some_and(|kept| kept.strained);
+            let mut ent = commands.spawn((
+                Part {
+                    id: part.id.clone(),
+                    proto: proto.name.clone(),
+                    mass,
+                },
+                PartThermal {
+                    temperature: kept
+                        .map_or(PartThermal::INITIAL_TEMPERATURE, |kept| kept.temperature),
+                    max_temperature: proto.thermal.max_temperature,
+                    specific_heat: proto.thermal.specific_heat,
+                    emissivity: proto.thermal.emissivity,
+                    size,
+                },
+                hull,
+                ChildOf(vessel),
+                child_tf,
+            ));
+            let part_entity = ent.id();
+            mass_boxes.push((mass, translation.as_dvec3(), rotation.as_dquat(), size));
+            let mut part_aero = PartAero::default();
+            let aero_tf = PreciseTransform {
+                translation_mm: translation.as_dvec3().to_millimeters(),
+                rotation: rotation.as_dquat(),
+            };
+            match proto.aero_shape {
+                PartAeroShape::Box => {
+                    // parts are only ever quarter-turned, so they stay aligned with the vessel's axes
+                    part_aero.aero_box =
+                        Some((translation.as_dvec3(), (rotation.as_dquat() * size).abs()));
+                }
+                PartAeroShape::Cylinder => part_aero.shapes.push((
+                    aero_tf,
+                    MainBodyModel::Cylinder {
+                        radius: size.x.min(size.y) / 2.0,
+                        length: size.z,
+                    },
+                )),
+                PartAeroShape::Nosecone => part_aero.shapes.push((
+                    aero_tf,
+                    MainBodyModel::Nosecone {
+                        radius: size.x.min(size.y) / 2.0,
+                        length: size.z,
+                    },
+                )),
+                PartAeroShape::None => {}
+            }
+            collider_boxes.push(ColliderBox {
+                part: part_entity,
+                center: translation.as_dvec3(),
+                rotation: rotation.as_dquat(),
+                half_extents: proto.dimensions_dm.as_dvec3() / 20.0,
+                crash_tolerance: proto.collision.crash_tolerance,
+                restitution: proto.collision.restitution,
+                friction: proto.collision.friction,
+            });
             if proto.model == "cuboid" {
                 let cuboid = Mesh3d(meshes.add(Cuboid::new(
                     proto.dimensions_dm.x as f32 / 10.0,
@@ -148,22 +279,22 @@ fn handle_spawn_vessel(
             }
 
             for module in &proto.modules {
-                // TODO compute offset correctly with respect to the SHIP!
-                let mut mod_entity = commands.spawn((Module, ChildOf(vessel)));
+                let mut mod_entity = commands.spawn((
+                    Module { part: part_entity },
+                    ChildOf(vessel),
+                    Transform {
+                        translation: translation + rotation * module.offset.as_vec3(),
+                        rotation,
+                        ..default()
+                    },
+                ));
                 match module.kind.clone() {
                     PartModuleCfgInner::MagicTorquer { torque } => {
-                        mod_entity.insert((
-                            Torquer {
-                                offset: module.offset,
-                                ..default()
-                            },
-                            MagicTorquer { torque },
-                        ));
+                        mod_entity.insert(MagicTorquer { torque });
                     }
                     PartModuleCfgInner::MagicThruster { thrust, flame } => {
                         mod_entity.insert((
                             Thruster {
-                                offset: module.offset,
                                 direction: module.direction,
                                 ..default()
                             },
@@ -172,7 +303,7 @@ fn handle_spawn_vessel(
                         if let Some(flame) = flame {
                             match flame {
                                 ThrusterFlameCfg::Simple { radius, max_length } => {
-                                    mod_entity.insert(SimpleThrusterFlame {
+                                    mod_entity.with_child(SimpleThrusterFlame {
                                         radius,
                                         length_per_newton: max_length / (thrust as f32),
                                     });
@@ -187,7 +318,6 @@ fn handle_spawn_vessel(
                     } => {
                         mod_entity.insert((
                             Thruster {
-                                offset: module.offset,
                                 direction: module.direction,
                                 ..default()
                             },
@@ -206,47 +336,120 @@ fn handle_spawn_vessel(
                         consumable_tanks.add_tank(consumable, capacity * fraction, capacity);
                     }
                     PartModuleCfgInner::NuclearReactor(config) => {
+                        let (current_throttle, desired_throttle) =
+                            reactors.next().unwrap_or((0.0, 1.0));
                         mod_entity.insert(NuclearReactor {
                             config,
-                            current_throttle: 0.0,
-                            desired_throttle: 1.0,
+                            current_throttle,
+                            desired_throttle,
                         });
                     }
+                    PartModuleCfgInner::Wing(wing) => {
+                        let wing_tf = PreciseTransform {
+                            translation_mm: aero_tf.translation_mm
+                                + (aero_tf.rotation * module.offset).to_millimeters(),
+                            rotation: aero_tf.rotation,
+                        };
+                        part_aero.wings.push((wing_tf, wing));
+                    }
+                    PartModuleCfgInner::Ablator {
+                        mass,
+                        heat_of_ablation,
+                        char_temperature,
+                    } => {
+                        mod_entity.insert(Ablator {
+                            mass: ablators.next().unwrap_or(mass),
+                            heat_of_ablation,
+                            char_temperature,
+                        });
+                    }
+                    PartModuleCfgInner::GasEnvelope(cfg) => {
+                        let offset = aero_tf.rotation * module.offset;
+                        let mut envelope = GasEnvelope::new(cfg, translation.as_dvec3() + offset);
+                        if let Some(gas_mass) = envelopes.next() {
+                            envelope.gas_mass = gas_mass;
+                        }
+                        part_aero.shapes.push((
+                            PreciseTransform {
+                                translation_mm: aero_tf.translation_mm + offset.to_millimeters(),
+                                rotation: aero_tf.rotation,
+                            },
+                            MainBodyModel::Sphere(envelope.radius()),
+                        ));
+                        mod_entity.insert(envelope);
+                    }
                 }
             }
+            commands.entity(part_entity).insert(part_aero.clone());
+            part_aeros.push(part_aero);
+        }
+        if let Some(preserved) = preserved {
+            for &(consumable, amount) in &preserved.consumables {
+                consumable_tanks.set_amount(consumable, amount);
+            }
+            commands.entity(vessel).insert((
+                AngularVelocity(preserved.angular_velocity),
+                VesselControls {
+                    dir_fbw_target: preserved.dir_fbw_target,
+                    rot_fbw_target: preserved.rot_fbw_target,
+                    raw_throttle: preserved.throttle,
+                    raw_steering: preserved.steering,
+                    raw_buoyancy: preserved.buoyancy,
+                    ..default()
+                },
+            ));
         }
-        commands.entity(vessel).insert(consumable_tanks);
+        let (mass_props, _) = MassProps::from_boxes(mass_boxes);
+        commands.entity(vessel).insert((
+            mass_props,
+            consumable_tanks,
+            Collider::new(collider_boxes),
+            PartAero::assemble(&part_aeros),
+        ));
     }
 }
 
-fn spawn_vessels(
-    time: Res<Time>,
-    orrery: Res<Orrery>,
-    vessels: Res<LoadedVessels>,
-    mut spawn: EventWriter<SpawnVesselEvent>,
-) {
-    let epoch = sim_time(&time);
+/// What a part adds to its vessel's aerodynamic model, in the vessel's frame.
+#[derive(Component, Clone, Debug, Default)]
+pub struct PartAero {
+    /// Centre and size (m) of the part's box, if it's one. Boxes shield each other where they touch.
+    pub aero_box: Option<(DVec3, DVec3)>,
+    pub shapes: Vec<(PreciseTransform, MainBodyModel)>,
+    pub wings: Vec<(PreciseTransform, Wing)>,
+}
 
-    let earth_center_mm = orrery.solve_position("Pannea", epoch).unwrap();
-    let sun_center_mm = orrery.solve_position("Taale", epoch).unwrap();
-    let dir = (sun_center_mm - earth_center_mm).to_meters_64().normalize();
-    let earth_radius_m = orrery.get_body("Pannea").unwrap().radius;
-    let altitude_m = earth_radius_m + 144_000.0;
-    let spawn_offset_mm = (dir * altitude_m).to_millimeters();
-    let spawn_pos_mm = earth_center_mm + spawn_offset_mm;
+impl PartAero {
+    /// The aerodynamic model of a vessel made of parts.
+    pub fn assemble<'a>(parts: impl IntoIterator<Item = &'a PartAero>) -> AeroModel {
+        let parts = parts.into_iter().collect::<Vec<_>>();
+        let boxes = parts
+            .iter()
+            .filter_map(|part| part.aero_box)
+            .collect::<Vec<_>>();
+        AeroModel {
+            main: MainBodyModel::Composite(
+                MainBodyModel::shielded_boxes(&boxes)
+                    .into_iter()
+                    .chain(parts.iter().flat_map(|part| part.shapes.iter().cloned()))
+                    .collect(),
+            ),
+            wings: parts
+                .iter()
+                .flat_map(|part| part.wings.iter().cloned())
+                .collect(),
+        }
+    }
 
-    for i in 0..1000 {
-        spawn.write(SpawnVesselEvent {
-            cfg: vessels.vessels.get("dummy").unwrap().clone(),
-            name: "Dummy".into(),
-            location: PreciseTransform {
-                translation_mm: spawn_pos_mm
-                    + (DVec3::new(rand::random(), rand::random(), rand::random()) * 100.0)
-                        .to_millimeters(),
-                rotation: DQuat::default(),
-            },
-            camera_focus: i == 0,
-        });
+    /// Moves the part's contribution by an offset (m) in the vessel's frame.
+    pub fn translate(&mut self, offset: DVec3) {
+        let offset_mm = offset.to_millimeters();
+        if let Some((center, _)) = &mut self.aero_box {
+            *center += offset;
+        }
+        let tfs = self.shapes.iter_mut().map(|(tf, _)| tf);
+        for tf in tfs.chain(self.wings.iter_mut().map(|(tf, _)| tf)) {
+            tf.translation_mm += offset_mm;
+        }
     }
 }
 
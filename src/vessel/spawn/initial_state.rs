@@ -0,0 +1,239 @@
+use bevy::math::{DMat3, DQuat, DVec3};
+use hifitime::Epoch;
+use smol_str::SmolStr;
+
+use crate::{
+    orrery::{Orbit, Orrery},
+    physics::geodesy::{Attitude, Geodetic, surface_transform},
+    precision::{PreciseTransform, ToMillimetersExt},
+};
+
+/// Where a vessel starts, and how it moves, resolved through the orrery when it's spawned.
+#[derive(Clone, Debug)]
+pub enum InitialState {
+    /// An exact pose, moving at a velocity (m/s) in the inertial frame.
+    Inertial {
+        transform: PreciseTransform,
+        velocity: DVec3,
+    },
+    /// Over a body, moving relative to its surface at a velocity (m/s) in the local north–east–down frame.
+    Surface {
+        body: SmolStr,
+        position: Geodetic,
+        attitude: Attitude,
+        velocity_ned: DVec3,
+    },
+    /// On an orbit around a body, given by its Keplerian elements, facing prograde with its top away from the body.
+    Orbit { body: SmolStr, orbit: Orbit },
+    /// On a circular orbit at an altitude (m) above a body, crossing its equator northwards at a longitude (rad) with
+    /// an inclination (rad) to it. Faces prograde, like [`InitialState::Orbit`].
+    Circular {
+        body: SmolStr,
+        altitude: f64,
+        inclination: f64,
+        longitude: f64,
+    },
+    /// Next to another initial state, at an offset (m) and velocity (m/s) in the axes of the vessel there, and
+    /// facing the same way.
+    Relative {
+        to: Box<InitialState>,
+        offset: DVec3,
+        velocity: DVec3,
+    },
+}
+
+/// At rest in the inertial frame.
+impl From<PreciseTransform> for InitialState {
+    fn from(transform: PreciseTransform) -> Self {
+        Self::Inertial {
+            transform,
+            velocity: DVec3::ZERO,
+        }
+    }
+}
+
+impl InitialState {
+    /// The pose and velocity (m/s) of the vessel at an epoch, or None if the body is not found or the orbit has no
+    /// size.
+    pub fn resolve(&self, orrery: &Orrery, epoch: Epoch) -> Option<(PreciseTransform, DVec3)> {
+        match self {
+            Self::Inertial {
+                transform,
+                velocity,
+            } => Some((*transform, *velocity)),
+            Self::Surface {
+                body,
+                position,
+                attitude,
+                velocity_ned,
+            } => surface_transform(orrery, body, position, attitude, *velocity_ned, epoch),
+            Self::Orbit { body, orbit } => {
+                let (position, velocity) = orrery.solve_orbit(body, orbit, epoch)?;
+                orbiting(orrery, body, position, velocity, epoch)
+            }
+            Self::Circular {
+                body,
+                altitude,
+                inclination,
+                longitude,
+            } => {
+                let cfg = orrery.get_body(body)?;
+                let node = Geodetic {
+                    latitude: 0.0,
+                    longitude: *longitude,
+                    altitude: *altitude,
+                };
+                let radius = cfg.radius + altitude;
+                if radius <= 0.0 {
+                    return None;
+                }
+                // the equator is that of the body-fixed frame, at this instant
+                let rotation = orrery.solve_rotation(body, epoch)?;
+                let enu = node.enu();
+                let direction = enu.x_axis * inclination.cos() + enu.y_axis * inclination.sin();
+                let speed = (cfg.mu / radius).sqrt();
+                orbiting(
+                    orrery,
+                    body,
+                    rotation * node.to_body_fixed(cfg.radius),
+                    rotation * direction * speed,
+                    epoch,
+                )
+            }
+            Self::Relative {
+                to,
+                offset,
+                velocity,
+            } => {
+                let (transform, base_velocity) = to.resolve(orrery, epoch)?;
+                let transform = PreciseTransform {
+                    translation_mm: transform.translation_mm
+                        + (transform.rotation * *offset).to_millimeters(),
+                    rotation: transform.rotation,
+                };
+                Some((transform, base_velocity + transform.rotation * *velocity))
+            }
+        }
+    }
+}
+
+/// The pose and velocity (m/s) of a vessel facing prograde, given its position (m) and velocity (m/s) relative to the
+/// body it orbits.
+fn orbiting(
+    orrery: &Orrery,
+    body: &str,
+    position: DVec3,
+    velocity: DVec3,
+    epoch: Epoch,
+) -> Option<(PreciseTransform, DVec3)> {
+    let transform = PreciseTransform {
+        translation_mm: orrery.solve_position(body, epoch)? + position.to_millimeters(),
+        rotation: prograde(position, velocity),
+    };
+    Some((
+        transform,
+        orrery.solve_absolute_velocity(body, epoch)? + velocity,
+    ))
+}
+
+/// The rotation of a vessel whose nose points along its velocity and whose top points away from the body it orbits,
+/// given its position (m) and velocity (m/s) relative to that body.
+fn prograde(position: DVec3, velocity: DVec3) -> DQuat {
+    let forward = velocity.normalize_or(DVec3::NEG_Z);
+    let up = position
+        .reject_from_normalized(forward)
+        .normalize_or(DVec3::Y);
+    DQuat::from_mat3(&DMat3::from_cols(forward.cross(up), up, -forward))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precision::ToMetersExt;
+
+    fn planet() -> Orrery {
+        let yaml = r#"
+name: "planet"
+bodies:
+  - name: "Star"
+    class: star
+    lumens: 3.828e26
+    mass: "1 massSol"
+  - name: "Planet"
+    class: planet
+    parent: "Star"
+    mass: "1 massEarth"
+    radius: 6.4e6
+    semi_major: "1 au"
+    rotation_period: "24 h"
+    obliquity: 23.0
+"#;
+        Orrery::init(
+            serde_yml::from_str(yaml).unwrap(),
+            Epoch::from_tai_seconds(0.0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn circular_orbit_is_circular() {
+        let orrery = planet();
+        let epoch = Epoch::from_tai_seconds(5000.0);
+        let state = InitialState::Circular {
+            body: "Planet".into(),
+            altitude: 400e3,
+            inclination: 0.9,
+            longitude: 2.0,
+        };
+        let (transform, velocity) = state.resolve(&orrery, epoch).unwrap();
+        let position = (transform.translation_mm - orrery.solve_position("Planet", epoch).unwrap())
+            .to_meters_64();
+        let velocity = velocity - orrery.solve_absolute_velocity("Planet", epoch).unwrap();
+        let mu = orrery.get_body("Planet").unwrap().mu;
+        assert!((position.length() / 6.8e6 - 1.0).abs() < 1e-9);
+        assert!((velocity.length() / (mu / 6.8e6).sqrt() - 1.0).abs() < 1e-12);
+        assert!(position.dot(velocity).abs() < 1e-6 * position.length() * velocity.length());
+        // inclined to the equator, and crossing it northwards
+        let spin_axis = orrery.solve_rotation("Planet", epoch).unwrap() * DVec3::Z;
+        let normal = position.cross(velocity).normalize();
+        assert!((normal.dot(spin_axis) - 0.9f64.cos()).abs() < 1e-9);
+        assert!(position.dot(spin_axis).abs() < 1e-3);
+        assert!(velocity.dot(spin_axis) > 0.0);
+        // facing prograde, with its top up
+        assert!((transform.rotation * DVec3::NEG_Z).abs_diff_eq(velocity.normalize(), 1e-9));
+        assert!((transform.rotation * DVec3::Y).abs_diff_eq(position.normalize(), 1e-9));
+
+        let too_low = InitialState::Circular {
+            body: "Planet".into(),
+            altitude: -7e6,
+            inclination: 0.0,
+            longitude: 0.0,
+        };
+        assert!(too_low.resolve(&orrery, epoch).is_none());
+    }
+
+    #[test]
+    fn relative_states_follow_their_reference() {
+        let orrery = planet();
+        let epoch = Epoch::from_tai_seconds(0.0);
+        let reference = InitialState::Inertial {
+            transform: PreciseTransform {
+                translation_mm: bevy::math::I64Vec3::new(1_000, 2_000, 3_000),
+                rotation: DQuat::from_rotation_y(std::f64::consts::FRAC_PI_2),
+            },
+            velocity: DVec3::X,
+        };
+        let relative = InitialState::Relative {
+            to: Box::new(reference),
+            offset: DVec3::new(0.0, 0.0, 50.0),
+            velocity: DVec3::new(0.0, 0.0, -0.5),
+        };
+        let (transform, velocity) = relative.resolve(&orrery, epoch).unwrap();
+        // the reference's +Z points along +X
+        assert_eq!(
+            transform.translation_mm,
+            bevy::math::I64Vec3::new(51_000, 2_000, 3_000)
+        );
+        assert!(velocity.abs_diff_eq(DVec3::X * 0.5, 1e-12));
+    }
+}
@@ -0,0 +1,270 @@
+use bevy::{math::DVec3, prelude::*};
+
+use crate::{
+    GameState,
+    physics::{
+        MassProps,
+        aerodynamics::{AeroEnv, calc_aerodynamics},
+    },
+    precision::PreciseTransform,
+    vessel::{DestroyPartEvent, Part, PartFailure, Vessel, modules::Module},
+};
+
+/// Sutton–Graves constant for stagnation-point heating in air-like atmospheres, in kg^½ m⁻¹.
+const SUTTON_GRAVES: f64 = 1.7415e-4;
+/// Stefan–Boltzmann constant, in W m⁻² K⁻⁴.
+const STEFAN_BOLTZMANN: f64 = 5.670_374e-8;
+/// Temperature (K) of empty space.
+const SPACE_TEMPERATURE: f64 = 3.0;
+/// Depth (m) behind the vessel's leading edge over which parts become shielded from the flow.
+const WAKE_LENGTH: f64 = 2.0;
+
+pub(super) fn run_thermal(app: &mut App) {
+    app.add_systems(
+        FixedUpdate,
+        heat_parts
+            .after(calc_aerodynamics)
+            .run_if(in_state(GameState::Game)),
+    );
+}
+
+/// Thermal state of a part, whose skin is assumed to heat up evenly.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PartThermal {
+    /// Skin temperature, in K.
+    pub temperature: f64,
+    /// Skin temperature (K) beyond which the part is destroyed.
+    pub max_temperature: f64,
+    /// Specific heat capacity, in J kg⁻¹ K⁻¹.
+    pub specific_heat: f64,
+    pub emissivity: f64,
+    /// Size (m) of the part along its own axes.
+    pub size: DVec3,
+}
+
+impl PartThermal {
+    pub const INITIAL_TEMPERATURE: f64 = 290.0;
+
+    /// Area (m²) the part presents to a flow along a direction in its own frame.
+    fn frontal_area(&self, dir: DVec3) -> f64 {
+        let s = self.size;
+        dir.x.abs() * s.y * s.z + dir.y.abs() * s.x * s.z + dir.z.abs() * s.x * s.y
+    }
+
+    /// Half the part's extent along a direction in its own frame.
+    fn half_depth(&self, dir: DVec3) -> f64 {
+        0.5 * (dir.abs() * self.size).element_sum()
+    }
+
+    fn surface_area(&self) -> f64 {
+        let s = self.size;
+        2.0 * (s.x * s.y + s.y * s.z + s.z * s.x)
+    }
+
+    /// Effective nose radius (m) for stagnation-point heating.
+    fn nose_radius(&self) -> f64 {
+        (0.5 * self.size.min_element()).max(0.05)
+    }
+}
+
+/// Ablative material on a part, which holds the part's skin at its char temperature while it burns away.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Ablator {
+    /// Remaining mass, in kg.
+    pub mass: f64,
+    /// Heat absorbed per kilogram ablated, in J/kg.
+    pub heat_of_ablation: f64,
+    /// Skin temperature (K) at which the ablator starts to burn away.
+    pub char_temperature: f64,
+}
+
+/// Sutton–Graves stagnation-point heat flux (W/m²) on a nose of a radius (m), at an air density (kg/m³) and speed
+/// (m/s).
+fn stagnation_flux(density: f64, nose_radius: f64, speed: f64) -> f64 {
+    SUTTON_GRAVES * (density / nose_radius).sqrt() * speed.powi(3)
+}
+
+/// Heats parts through the stagnation-point flux (Sutton–Graves) and convection, and cools them by radiation.
+fn heat_parts(
+    time: Res<Time>,
+    mut vessels: Query<(&AeroEnv, &PreciseTransform, &Children, &mut MassProps), With<Vessel>>,
+    mut parts: Query<(&Transform, &mut Part, &mut PartThermal)>,
+    mut ablators: Query<(&Module, &mut Ablator)>,
+    mut destroy: EventWriter<DestroyPartEvent>,
+) {
+    let dt = time.delta_secs_f64();
+    for (aero, ptf, children, mut mass_props) in vessels.iter_mut() {
+        let speed = aero.airspeed.length();
+        // direction of travel through the air, in the vessel's frame
+        let heading = if speed > 0.0 {
+            ptf.rotation.inverse() * (aero.airspeed / speed)
+        } else {
+            DVec3::NEG_Z
+        };
+        let part_heading = |tf: &Transform| {
+            (
+                tf.rotation.as_dquat().inverse() * heading,
+                tf.translation.as_dvec3(),
+            )
+        };
+
+        // the leading edge of the vessel, along the direction of travel
+        let front = parts
+            .iter_many(children)
+            .map(|(tf, _, thermal)| {
+                let (dir, center) = part_heading(tf);
+                center.dot(heading) + thermal.half_depth(dir)
+            })
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let ambient = aero.temperature.max(SPACE_TEMPERATURE);
+        // forced convection, scaling roughly with the square root of the mass flux
+        let convection = 10.0 * (aero.density * speed.max(1.0) / 1.2).sqrt();
+
+        for &part_entity in children {
+            let Ok((tf, mut part, mut thermal)) = parts.get_mut(part_entity) else {
+                continue;
+            };
+            let (dir, center) = part_heading(tf);
+            let depth = front - (center.dot(heading) + thermal.half_depth(dir));
+            let exposure = (-depth.max(0.0) / WAKE_LENGTH).exp();
+
+            let stagnation_flux = stagnation_flux(aero.density, thermal.nose_radius(), speed);
+            let surface = thermal.surface_area();
+            let t = thermal.temperature;
+            // the flux falls off away from the stagnation point, to about half on average
+            let mut heat = 0.5 * stagnation_flux * thermal.frontal_area(dir) * exposure
+                + convection * surface * (aero.temperature - t)
+                - thermal.emissivity * STEFAN_BOLTZMANN * surface * (t.powi(4) - ambient.powi(4));
+
+            if heat > 0.0
+                && let Some(ablator_entity) = children.iter().find(|&child| {
+                    ablators
+                        .get(child)
+                        .is_ok_and(|(module, _)| module.part == part_entity)
+                })
+                && let Ok((_, mut ablator)) = ablators.get_mut(ablator_entity)
+                && ablator.mass > 0.0
+                && t >= ablator.char_temperature
+            {
+                let burnt = (heat * dt / ablator.heat_of_ablation).min(ablator.mass);
+                ablator.mass -= burnt;
+                part.mass -= burnt;
+                mass_props.mass -= burnt;
+                heat -= burnt * ablator.heat_of_ablation / dt;
+            }
+
+            thermal.temperature += heat * dt / (part.mass.max(1e-3) * thermal.specific_heat);
+            if thermal.temperature > thermal.max_temperature {
+                destroy.write(DestroyPartEvent {
+                    part: part_entity,
+                    cause: PartFailure::Overheat,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn sutton_graves_flux() {
+        // about 60 W/cm² for a 1 m nose at 7 km/s, high in an Earth-like atmosphere
+        let flux = stagnation_flux(1e-4, 1.0, 7000.0);
+        assert!((flux - 1.7415e-4 * 0.01 * 7000f64.powi(3)).abs() < 1e-6);
+        assert!((5.9e5..6.0e5).contains(&flux), "{flux}");
+        // cubic in speed, square root in density, and inverse square root in nose radius
+        assert!((stagnation_flux(1e-4, 1.0, 14000.0) / flux - 8.0).abs() < 1e-12);
+        assert!((stagnation_flux(4e-4, 1.0, 7000.0) / flux - 2.0).abs() < 1e-12);
+        assert!((stagnation_flux(1e-4, 4.0, 7000.0) / flux - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ablators_hold_the_skin_at_their_char_temperature() {
+        let mut app = App::new();
+        app.add_event::<DestroyPartEvent>()
+            .insert_resource(Time::<()>::default())
+            .add_systems(Update, heat_parts);
+        let world = app.world_mut();
+        let vessel = world
+            .spawn((
+                Vessel {
+                    class_name: "test".into(),
+                    vessel_name: "test".into(),
+                },
+                PreciseTransform::default(),
+                AeroEnv {
+                    density: 2e-3,
+                    temperature: 250.0,
+                    airspeed: DVec3::NEG_Z * 8000.0,
+                    ..Default::default()
+                },
+            ))
+            .id();
+        let thermal = PartThermal {
+            temperature: 1450.0,
+            max_temperature: 1500.0,
+            specific_heat: 100.0,
+            emissivity: 0.8,
+            size: DVec3::ONE,
+        };
+        // side by side, both facing the flow
+        let mut part = |x: f32| {
+            world
+                .spawn((
+                    Part {
+                        id: "part".into(),
+                        proto: "part".into(),
+                        mass: 100.0,
+                    },
+                    thermal,
+                    Transform::from_xyz(x, 0.0, 0.0),
+                    ChildOf(vessel),
+                ))
+                .id()
+        };
+        let (shielded, bare) = (part(-1.0), part(1.0));
+        let ablator = world
+            .spawn((
+                Module { part: shielded },
+                Ablator {
+                    mass: 10.0,
+                    heat_of_ablation: 1e7,
+                    char_temperature: 900.0,
+                },
+                ChildOf(vessel),
+            ))
+            .id();
+        world.entity_mut(vessel).insert(MassProps {
+            mass: 210.0,
+            ..Default::default()
+        });
+
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        app.update();
+
+        let world = app.world();
+        let flux = stagnation_flux(2e-3, 0.5, 8000.0);
+        let burnt = 10.0 - world.get::<Ablator>(ablator).unwrap().mass;
+        // half the stagnation flux on the 1 m² face, less convection and radiation, over a second
+        assert!(burnt > 0.0 && burnt < 0.5 * flux / 1e7, "{burnt}");
+        assert!((world.get::<Part>(shielded).unwrap().mass - (100.0 - burnt)).abs() < 1e-9);
+        assert!((world.get::<MassProps>(vessel).unwrap().mass - (210.0 - burnt)).abs() < 1e-9);
+        let temperature = |part| world.get::<PartThermal>(part).unwrap().temperature;
+        assert!((temperature(shielded) - 1450.0).abs() < 1e-6);
+        assert!(temperature(bare) > 1500.0);
+
+        // the bare part overheated
+        let events = world.resource::<Events<DestroyPartEvent>>();
+        let destroyed = events
+            .iter_current_update_events()
+            .map(|evt| (evt.part, evt.cause))
+            .collect::<Vec<_>>();
+        assert_eq!(destroyed, vec![(bare, PartFailure::Overheat)]);
+    }
+}